@@ -0,0 +1,42 @@
+#![feature(test)]
+
+extern crate test;
+
+use openh264::formats::{NV12Slices, YUVBuffer};
+use test::Bencher;
+
+#[bench]
+fn convert_nv12_to_yuv_1920x1080(b: &mut Bencher) {
+    let y = (0..1920 * 1080).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+    let uv = (0..1920 * 540).map(|i| (i * 89 % 241) as u8).collect::<Vec<_>>();
+    let nv12 = NV12Slices::new(&y, &uv, (1920, 1080), (1920, 1920));
+    let mut converter = YUVBuffer::new(1920, 1080);
+
+    b.iter(|| {
+        converter.read_nv12(&nv12);
+    });
+}
+
+#[bench]
+fn convert_nv21_to_yuv_1918x1078(b: &mut Bencher) {
+    let y = (0..1920 * 1078).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+    let vu = (0..1920 * 539).map(|i| (i * 89 % 241) as u8).collect::<Vec<_>>();
+    let nv21 = NV12Slices::new_nv21(&y, &vu, (1918, 1078), (1920, 1920));
+    let mut converter = YUVBuffer::new(1918, 1078);
+
+    b.iter(|| {
+        converter.read_nv12(&nv21);
+    });
+}
+
+#[bench]
+fn convert_nv12_to_yuv_320x240(b: &mut Bencher) {
+    let y = (0..320 * 240).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+    let uv = (0..320 * 120).map(|i| (i * 89 % 241) as u8).collect::<Vec<_>>();
+    let nv12 = NV12Slices::new(&y, &uv, (320, 240), (320, 320));
+    let mut converter = YUVBuffer::new(320, 240);
+
+    b.iter(|| {
+        converter.read_nv12(&nv12);
+    });
+}
@@ -0,0 +1,39 @@
+#![feature(test)]
+
+extern crate test;
+
+use openh264::formats::{EncoderInput, UyvySliceU8, YUVBuffer, YuyvSliceU8};
+use test::Bencher;
+
+#[bench]
+fn convert_yuyv_to_yuv_1920x1080(b: &mut Bencher) {
+    let data = (0..1920 * 2 * 1080).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+    let yuyv = YuyvSliceU8::new(&data, (1920, 1080));
+    let mut buffer = Some(YUVBuffer::new(1920, 1080));
+
+    b.iter(|| {
+        yuyv.as_yuv(&mut buffer);
+    });
+}
+
+#[bench]
+fn convert_uyvy_to_yuv_1919x1079(b: &mut Bencher) {
+    let data = (0..1920 * 2 * 1079).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+    let uyvy = UyvySliceU8::with_stride(&data, (1919, 1079), 1920 * 2);
+    let mut buffer = Some(YUVBuffer::new(1919, 1079));
+
+    b.iter(|| {
+        uyvy.as_yuv(&mut buffer);
+    });
+}
+
+#[bench]
+fn convert_yuyv_to_yuv_320x240(b: &mut Bencher) {
+    let data = (0..320 * 2 * 240).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+    let yuyv = YuyvSliceU8::new(&data, (320, 240));
+    let mut buffer = Some(YUVBuffer::new(320, 240));
+
+    b.iter(|| {
+        yuyv.as_yuv(&mut buffer);
+    });
+}
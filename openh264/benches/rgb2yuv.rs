@@ -4,7 +4,7 @@ extern crate test;
 
 use openh264::OpenH264API;
 use openh264::decoder::{Decoder, DecoderConfig};
-use openh264::formats::{RgbSliceU8, YUVBuffer, YUVSource};
+use openh264::formats::{RgbSliceU8, RgbaSliceU8, YUVBuffer, YUVSource};
 use test::Bencher;
 
 #[bench]
@@ -27,6 +27,20 @@ fn convert_rgb8_to_yuv_512x512(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn convert_rgba8_to_yuv_512x512(b: &mut Bencher) {
+    let src = include_bytes!("../tests/data/lenna_512x512.rgb");
+    let rgba = src
+        .chunks_exact(3)
+        .flat_map(|px| [px[0], px[1], px[2], 255])
+        .collect::<Vec<_>>();
+    let rgba_source = RgbaSliceU8::new(&rgba, (512, 512));
+
+    b.iter(|| {
+        _ = YUVBuffer::from_rgb8_source(rgba_source);
+    });
+}
+
 #[bench]
 #[cfg(feature = "source")]
 fn convert_rgb_to_yuv_1920x1080(b: &mut Bencher) {
@@ -64,3 +78,68 @@ fn convert_rgb8_to_yuv_1920x1080(b: &mut Bencher) {
         converter.read_rgb8(rgb_source);
     });
 }
+
+#[bench]
+#[cfg(feature = "source")]
+fn convert_rgba8_to_yuv_1920x1080(b: &mut Bencher) {
+    let source = include_bytes!("../tests/data/single_1920x1080_cabac.h264");
+
+    let api = OpenH264API::from_source();
+    let config = DecoderConfig::default();
+    let mut decoder = Decoder::with_api_config(api, config).unwrap();
+    let yuv = decoder.decode(source.as_slice()).unwrap().unwrap();
+    let mut rgba = vec![0u8; yuv.rgba8_len()];
+    yuv.write_rgba8(&mut rgba);
+    let rgba_source = RgbaSliceU8::new(&rgba, (1920, 1080));
+    let mut converter = YUVBuffer::new(1920, 1080);
+
+    b.iter(|| {
+        converter.read_rgb8(rgba_source);
+    });
+}
+
+// Widths that are not a multiple of 8 take the scalar path, so these compare both on generated data.
+
+#[bench]
+fn convert_rgb8_to_yuv_generated_1920x1080(b: &mut Bencher) {
+    let rgb = (0..1920 * 1080 * 3).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+    let rgb_source = RgbSliceU8::new(&rgb, (1920, 1080));
+    let mut converter = YUVBuffer::new(1920, 1080);
+
+    b.iter(|| {
+        converter.read_rgb8(rgb_source);
+    });
+}
+
+#[bench]
+fn convert_rgb8_to_yuv_generated_1918x1080(b: &mut Bencher) {
+    let rgb = (0..1918 * 1080 * 3).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+    let rgb_source = RgbSliceU8::new(&rgb, (1918, 1080));
+    let mut converter = YUVBuffer::new(1918, 1080);
+
+    b.iter(|| {
+        converter.read_rgb8(rgb_source);
+    });
+}
+
+#[bench]
+fn convert_rgba8_to_yuv_generated_1920x1080(b: &mut Bencher) {
+    let rgba = (0..1920 * 1080 * 4).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+    let rgba_source = RgbaSliceU8::new(&rgba, (1920, 1080));
+    let mut converter = YUVBuffer::new(1920, 1080);
+
+    b.iter(|| {
+        converter.read_rgb8(rgba_source);
+    });
+}
+
+#[bench]
+fn convert_rgba8_to_yuv_generated_1918x1080(b: &mut Bencher) {
+    let rgba = (0..1918 * 1080 * 4).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+    let rgba_source = RgbaSliceU8::new(&rgba, (1918, 1080));
+    let mut converter = YUVBuffer::new(1918, 1080);
+
+    b.iter(|| {
+        converter.read_rgb8(rgba_source);
+    });
+}
@@ -48,19 +48,25 @@
 //! # }
 //! ```
 
+use crate::encoder::{ColorPrimaries, MatrixCoefficients, SampleAspectRatio, TransferCharacteristics};
 use crate::error::NativeErrorExt;
-use crate::formats::yuv2rgb::{write_rgb8_f32x8, write_rgb8_scalar, write_rgba8_f32x8, write_rgba8_scalar};
-// use crate::formats::yuv2rgb::{write_rgb8_f32x8, write_rgb8_f32x8_par, write_rgb8_scalar, write_rgb8_scalar_par};
-use crate::formats::{YUVSlices, YUVSource};
-use crate::{Error, OpenH264API, Timestamp};
+use crate::formats::yuv2rgb;
+use crate::formats::{ColorMatrix, ColorSpace, YUVSlices, YUVSource};
+use crate::{Error, OpenH264API, Timestamp, nal_units, sps};
 use openh264_sys2::{
-    API, DECODER_OPTION, DECODER_OPTION_ERROR_CON_IDC, DECODER_OPTION_NUM_OF_FRAMES_REMAINING_IN_BUFFER,
-    DECODER_OPTION_NUM_OF_THREADS, DECODER_OPTION_TRACE_LEVEL, DECODING_STATE, ISVCDecoder, ISVCDecoderVtbl, SBufferInfo,
-    SDecodingParam, SParserBsInfo, SSysMEMBuffer, SVideoProperty, TagBufferInfo, WELS_LOG_DETAIL, WELS_LOG_QUIET,
-    videoFormatI420,
+    API, DECODER_OPTION, DECODER_OPTION_ERROR_CON_IDC, DECODER_OPTION_FRAME_NUM, DECODER_OPTION_GET_STATISTICS,
+    DECODER_OPTION_IDR_PIC_ID, DECODER_OPTION_IS_REF_PIC, DECODER_OPTION_LTR_MARKED_FRAME_NUM, DECODER_OPTION_LTR_MARKING_FLAG,
+    DECODER_OPTION_NUM_OF_FRAMES_REMAINING_IN_BUFFER, DECODER_OPTION_NUM_OF_THREADS, DECODER_OPTION_STATISTICS_LOG_INTERVAL,
+    DECODER_OPTION_TEMPORAL_ID, DECODER_OPTION_TRACE_LEVEL, DECODING_STATE, ERROR_CON_IDC, ISVCDecoder, ISVCDecoderVtbl,
+    SBufferInfo, SDecoderStatistics, SDecodingParam, SParserBsInfo, SSysMEMBuffer, SVideoProperty, TagBufferInfo,
+    WELS_LOG_DETAIL, WELS_LOG_QUIET, dsDataErrorConcealed, videoFormatI420,
 };
 use std::os::raw::{c_int, c_long, c_uchar, c_void};
 use std::ptr::{addr_of_mut, from_mut, null, null_mut};
+use std::sync::{Arc, Mutex, PoisonError};
+
+#[cfg(feature = "log")]
+use openh264_sys2::{DECODER_OPTION_TRACE_CALLBACK, DECODER_OPTION_TRACE_CALLBACK_CONTEXT, WelsTraceCallback};
 
 /// Convenience wrapper with guaranteed function pointers for easy access.
 ///
@@ -175,6 +181,51 @@ impl Flush {
     }
 }
 
+/// How the decoder handles missing or corrupt data, e.g., after packet loss.
+///
+/// With concealment enabled, the decoder fills in the damaged parts of a frame and returns it flagged as
+/// [concealed](DecodedYUV::is_concealed), instead of failing until the next IDR frame.
+///
+/// The variant documentation was adapted from the OpenH264 project.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ErrorConcealment {
+    /// Return an error for corrupt frames (default).
+    #[default]
+    Disabled,
+    /// Copy the whole previous frame into a damaged frame.
+    FrameCopy,
+    /// Copy the damaged slices from the previous frame.
+    SliceCopy,
+    /// Like [`FrameCopy`](Self::FrameCopy), also concealing a lost IDR frame with the frame before it.
+    FrameCopyCrossIdr,
+    /// Like [`SliceCopy`](Self::SliceCopy), also concealing a lost IDR frame with the frame before it.
+    SliceCopyCrossIdr,
+    /// Like [`SliceCopyCrossIdr`](Self::SliceCopyCrossIdr), but freezes on the last good frame if the
+    /// resolution changed.
+    SliceCopyCrossIdrFreezeResChange,
+    /// Like [`SliceCopyCrossIdr`](Self::SliceCopyCrossIdr), but uses the motion vectors of neighboring blocks to
+    /// predict damaged blocks from the previous frame.
+    SliceMvCopyCrossIdr,
+    /// Like [`SliceMvCopyCrossIdr`](Self::SliceMvCopyCrossIdr), but freezes on the last good frame if the
+    /// resolution changed.
+    SliceMvCopyCrossIdrFreezeResChange,
+}
+
+impl ErrorConcealment {
+    const fn to_c(self) -> ERROR_CON_IDC {
+        match self {
+            Self::Disabled => openh264_sys2::ERROR_CON_DISABLE,
+            Self::FrameCopy => openh264_sys2::ERROR_CON_FRAME_COPY,
+            Self::SliceCopy => openh264_sys2::ERROR_CON_SLICE_COPY,
+            Self::FrameCopyCrossIdr => openh264_sys2::ERROR_CON_FRAME_COPY_CROSS_IDR,
+            Self::SliceCopyCrossIdr => openh264_sys2::ERROR_CON_SLICE_COPY_CROSS_IDR,
+            Self::SliceCopyCrossIdrFreezeResChange => openh264_sys2::ERROR_CON_SLICE_COPY_CROSS_IDR_FREEZE_RES_CHANGE,
+            Self::SliceMvCopyCrossIdr => openh264_sys2::ERROR_CON_SLICE_MV_COPY_CROSS_IDR,
+            Self::SliceMvCopyCrossIdrFreezeResChange => openh264_sys2::ERROR_CON_SLICE_MV_COPY_CROSS_IDR_FREEZE_RES_CHANGE,
+        }
+    }
+}
+
 /// Configuration for the [`Decoder`].
 ///
 /// Setting missing? Please file a PR!
@@ -185,6 +236,7 @@ pub struct DecoderConfig {
     num_threads: DECODER_OPTION,
     debug: DECODER_OPTION,
     error_concealment: DECODER_OPTION,
+    statistics_log_interval: Option<u32>,
     flush_after_decode: Flush,
 }
 
@@ -209,6 +261,7 @@ impl DecoderConfig {
             num_threads: 0,
             debug: WELS_LOG_QUIET,
             error_concealment: 0,
+            statistics_log_interval: None,
             flush_after_decode: Flush::Flush,
         }
     }
@@ -229,11 +282,28 @@ impl DecoderConfig {
     }
 
     /// Enables detailed console logging inside OpenH264.
+    ///
+    /// With the `log` feature, output is forwarded to the `log` crate instead. OpenH264's error, warning, info
+    /// and debug messages keep their level, more detailed ones are logged as `trace`. Nothing is logged unless
+    /// this is enabled, regardless of the `log` crate's max level.
     pub const fn debug(mut self, value: bool) -> Self {
         self.debug = if value { WELS_LOG_DETAIL } else { WELS_LOG_QUIET };
         self
     }
 
+    /// Sets how the decoder handles missing or corrupt data.
+    pub const fn error_concealment(mut self, value: ErrorConcealment) -> Self {
+        self.params.eEcActiveIdc = value.to_c();
+        self.error_concealment = value.to_c();
+        self
+    }
+
+    /// Sets the number of decoded frames after which OpenH264 logs its [statistics](Decoder::statistics).
+    pub const fn statistics_log_interval(mut self, num_frames: u32) -> Self {
+        self.statistics_log_interval = Some(num_frames);
+        self
+    }
+
     /// Sets the default flush behavior after decode operations..
     pub const fn flush_after_decode(mut self, flush_behavior: Flush) -> Self {
         self.flush_after_decode = flush_behavior;
@@ -268,6 +338,7 @@ impl DecodeOptions {
 pub struct Decoder {
     raw_api: DecoderRawAPI,
     config: DecoderConfig,
+    vui: VuiInfo,
 }
 
 impl Decoder {
@@ -293,6 +364,19 @@ impl Decoder {
     pub fn with_api_config(api: OpenH264API, mut config: DecoderConfig) -> Result<Self, Error> {
         let raw_api = DecoderRawAPI::new(api)?;
 
+        #[cfg(feature = "log")]
+        unsafe {
+            let mut callback: WelsTraceCallback = Some(crate::trace::decoder_callback);
+            let mut context = crate::trace::new_instance();
+
+            raw_api
+                .set_option(DECODER_OPTION_TRACE_CALLBACK, addr_of_mut!(callback).cast())
+                .ok()?;
+            raw_api
+                .set_option(DECODER_OPTION_TRACE_CALLBACK_CONTEXT, addr_of_mut!(context).cast())
+                .ok()?;
+        }
+
         // config.params.sVideoProperty.eVideoBsType = VIDEO_BITSTREAM_AVC;
 
         #[rustfmt::skip]
@@ -301,9 +385,17 @@ impl Decoder {
             raw_api.set_option(DECODER_OPTION_TRACE_LEVEL, addr_of_mut!(config.debug).cast()).ok()?;
             raw_api.set_option(DECODER_OPTION_NUM_OF_THREADS, addr_of_mut!(config.num_threads).cast()).ok()?;
             raw_api.set_option(DECODER_OPTION_ERROR_CON_IDC, addr_of_mut!(config.error_concealment).cast()).ok()?;
+
+            if let Some(mut interval) = config.statistics_log_interval {
+                raw_api.set_option(DECODER_OPTION_STATISTICS_LOG_INTERVAL, addr_of_mut!(interval).cast()).ok()?;
+            }
         };
 
-        Ok(Self { raw_api, config })
+        Ok(Self {
+            raw_api,
+            config,
+            vui: VuiInfo::default(),
+        })
     }
 
     /// Decodes a series of H.264 NAL packets and returns the latest picture.
@@ -343,21 +435,25 @@ impl Decoder {
         let mut buffer_info = SBufferInfo::default();
         let flush = self.config.flush_after_decode.should_flush(options);
 
-        unsafe {
-            self.raw_api
-                .decode_frame_no_delay(
-                    packet.as_ptr(),
-                    packet.len() as i32,
-                    from_mut(&mut dst).cast(),
-                    &raw mut buffer_info,
-                )
-                .ok()?;
+        // OpenH264 doesn't report the color information of the stream, so we look for it ourselves.
+        if let Some(vui) = nal_units(packet).find_map(sps::parse_vui) {
+            self.vui = vui;
         }
 
+        let concealed = unsafe {
+            concealed(self.raw_api.decode_frame_no_delay(
+                packet.as_ptr(),
+                packet.len() as i32,
+                from_mut(&mut dst).cast(),
+                &raw mut buffer_info,
+            ))?
+        };
+        let decoded_info = self.frame_info();
+
         match (buffer_info.iBufferStatus, flush) {
             // No outstanding images, but asked to flush, and flushable frames available?
             (0, true) if self.num_frames_in_buffer()? > 0 => {
-                let (dst, buffer_info) = self.flush_single_frame_raw()?;
+                let (dst, buffer_info, concealed) = self.flush_single_frame_raw()?;
 
                 if buffer_info.iBufferStatus == 0 {
                     return Err(Error::msg(
@@ -365,18 +461,41 @@ impl Decoder {
                     ));
                 }
 
-                unsafe { Ok(DecodedYUV::from_raw_open264_ptrs(&dst, &buffer_info)) }
+                let frame_info = self.output_frame_info(decoded_info)?;
+
+                unsafe {
+                    Ok(DecodedYUV::from_raw_open264_ptrs(
+                        &dst,
+                        &buffer_info,
+                        self.vui,
+                        concealed,
+                        frame_info,
+                    ))
+                }
             }
             // No outstanding images otherwise? Nothing to do.
             (0, _) => Ok(None),
             // Outstanding images otherwise? Return one.
-            _ => unsafe { Ok(DecodedYUV::from_raw_open264_ptrs(&dst, &buffer_info)) },
+            _ => {
+                let frame_info = self.output_frame_info(decoded_info)?;
+
+                unsafe {
+                    Ok(DecodedYUV::from_raw_open264_ptrs(
+                        &dst,
+                        &buffer_info,
+                        self.vui,
+                        concealed,
+                        frame_info,
+                    ))
+                }
+            }
         }
     }
 
     /// Flush and return all remaining frames in the buffer.
     ///
-    /// This function should be called after decoding all frames of a NAL stream.
+    /// This function should be called after decoding all frames of a NAL stream. The frames' [`FrameInfo`] is always
+    /// empty, as it can't be matched to the access units they were decoded from.
     ///
     /// # Errors
     ///
@@ -385,9 +504,11 @@ impl Decoder {
         let mut frames = Vec::new();
 
         for _ in 0..self.num_frames_in_buffer()? {
-            let (dst, buffer_info) = self.flush_single_frame_raw()?;
+            let (dst, buffer_info, concealed) = self.flush_single_frame_raw()?;
+            let frame_info = FrameInfo::default();
 
-            if let Some(image) = unsafe { DecodedYUV::from_raw_open264_ptrs(&dst, &buffer_info) } {
+            if let Some(image) = unsafe { DecodedYUV::from_raw_open264_ptrs(&dst, &buffer_info, self.vui, concealed, frame_info) }
+            {
                 frames.push(image);
             }
         }
@@ -395,6 +516,23 @@ impl Decoder {
         Ok(frames)
     }
 
+    /// Returns statistics about the decoding process so far.
+    ///
+    /// # Errors
+    ///
+    /// This should never error, but the underlying OpenH264 decoder has an error indication.
+    pub fn statistics(&self) -> Result<DecoderStatistics, Error> {
+        let mut stats = SDecoderStatistics::default();
+
+        unsafe {
+            self.raw_api
+                .get_option(DECODER_OPTION_GET_STATISTICS, addr_of_mut!(stats).cast())
+                .ok()?;
+        }
+
+        Ok(DecoderStatistics::from_native(&stats))
+    }
+
     /// Obtain the raw API for advanced use cases.
     ///
     /// When resorting to this call, please consider filing an issue / PR.
@@ -440,20 +578,71 @@ impl Decoder {
         Ok(num_frames as usize)
     }
 
+    /// Returns the metadata OpenH264 reports about the most recently decoded access unit.
+    fn frame_info(&self) -> FrameInfo {
+        let ltr_marking = self.int_option(DECODER_OPTION_LTR_MARKING_FLAG);
+
+        FrameInfo {
+            frame_num: self.int_option(DECODER_OPTION_FRAME_NUM).and_then(|x| u32::try_from(x).ok()),
+            idr_pic_id: self.int_option(DECODER_OPTION_IDR_PIC_ID).and_then(|x| u32::try_from(x).ok()),
+            temporal_id: self.int_option(DECODER_OPTION_TEMPORAL_ID).and_then(|x| u8::try_from(x).ok()),
+            is_reference: self.int_option(DECODER_OPTION_IS_REF_PIC) == Some(1),
+            ltr_marked_frame_num: match ltr_marking {
+                Some(1..) => self
+                    .int_option(DECODER_OPTION_LTR_MARKED_FRAME_NUM)
+                    .and_then(|x| u32::try_from(x).ok()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns `decoded` for a picture just returned by the decoder if that picture is the access unit decoded last,
+    /// or an empty [`FrameInfo`] if it was held back for reordering and belongs to an earlier one.
+    ///
+    /// OpenH264 only reports the metadata of the access unit decoded last, so this is only certain if that one
+    /// contained slices, and no other pictures remain buffered.
+    fn output_frame_info(&mut self, decoded: FrameInfo) -> Result<FrameInfo, Error> {
+        if decoded.temporal_id.is_some() && self.num_frames_in_buffer()? == 0 {
+            Ok(decoded)
+        } else {
+            Ok(FrameInfo::default())
+        }
+    }
+
+    /// Queries an integer option, `None` if the library doesn't support it.
+    fn int_option(&self, option: DECODER_OPTION) -> Option<c_int> {
+        let mut value: c_int = 0;
+
+        let result = unsafe { self.raw_api.get_option(option, addr_of_mut!(value).cast()) };
+
+        (result == 0).then_some(value)
+    }
+
     /// Attempts to flush a single frame (i.e., produce a new YUV from previously passed bitstream data), if available.
-    fn flush_single_frame_raw(&mut self) -> Result<([*mut u8; 3], TagBufferInfo), Error> {
+    ///
+    /// Also returns if the frame was concealed.
+    fn flush_single_frame_raw(&mut self) -> Result<([*mut u8; 3], TagBufferInfo, bool), Error> {
         let mut dst = [null_mut::<u8>(); 3];
         let mut buffer_info = SBufferInfo::default();
 
         unsafe {
-            self.raw_api()
-                .flush_frame(from_mut(&mut dst).cast(), &raw mut buffer_info)
-                .ok()?;
-            Ok((dst, buffer_info))
+            let concealed = concealed(self.raw_api().flush_frame(from_mut(&mut dst).cast(), &raw mut buffer_info))?;
+            Ok((dst, buffer_info, concealed))
         }
     }
 }
 
+/// Checks the state returned by a decode call, which is not an error if the decoder concealed the damage.
+///
+/// Returns if the output was concealed.
+fn concealed(state: DECODING_STATE) -> Result<bool, Error> {
+    if state & dsDataErrorConcealed != 0 {
+        Ok(true)
+    } else {
+        state.ok().map(|()| false)
+    }
+}
+
 impl Drop for Decoder {
     fn drop(&mut self) {
         // Safe because when we drop the pointer must have been initialized.
@@ -463,11 +652,141 @@ impl Drop for Decoder {
     }
 }
 
+/// Statistics of a [`Decoder`], as returned by [`Decoder::statistics()`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DecoderStatistics {
+    /// Width of the decoded frames.
+    pub width: u32,
+    /// Height of the decoded frames.
+    pub height: u32,
+    /// Average time needed to decode a frame, in milliseconds.
+    pub average_decoding_time_ms: f32,
+    /// Average time needed to decode a frame including frozen frames, in milliseconds.
+    pub actual_average_decoding_time_ms: f32,
+    /// Number of decoded frames.
+    pub decoded_frame_count: u32,
+    /// Number of times the resolution changed.
+    pub resolution_change_count: u32,
+    /// Number of correctly received IDR frames.
+    pub idr_count: u32,
+    /// Number of IDR frames lost entirely.
+    pub lost_idr_count: u32,
+    /// Number of frames with concealed errors, see [`ErrorConcealment`].
+    pub concealed_frame_count: u32,
+    /// Number of incomplete or lost IDR frames that were concealed.
+    pub concealed_idr_count: u32,
+    /// Average share of concealed area in concealed frames, in percent.
+    pub average_concealed_ratio: u32,
+    /// Average share of area affected by concealed errors propagating from reference frames, in percent.
+    pub average_propagated_concealed_ratio: u32,
+    /// Number of damaged IDR frames not shown but frozen on the previous frame, due to a resolution change.
+    pub frozen_idr_count: u32,
+    /// Number of damaged non-IDR frames not shown but frozen on the previous frame.
+    pub frozen_frame_count: u32,
+    /// Average luma QP of the decoded frames, `None` if no frame was decoded correctly.
+    pub average_luma_qp: Option<i32>,
+    /// Number of invalid SPS received.
+    pub invalid_sps_count: u32,
+    /// Number of invalid PPS received.
+    pub invalid_pps_count: u32,
+    /// Number of slices referencing an SPS that was not received.
+    pub missing_sps_count: u32,
+    /// Number of slices referencing a PPS that was not received.
+    pub missing_pps_count: u32,
+    /// `profile_idc` of the stream.
+    pub profile_idc: u32,
+    /// `level_idc` of the stream.
+    pub level_idc: u32,
+}
+
+impl DecoderStatistics {
+    const fn from_native(stats: &SDecoderStatistics) -> Self {
+        Self {
+            width: stats.uiWidth,
+            height: stats.uiHeight,
+            average_decoding_time_ms: stats.fAverageFrameSpeedInMs,
+            actual_average_decoding_time_ms: stats.fActualAverageFrameSpeedInMs,
+            decoded_frame_count: stats.uiDecodedFrameCount,
+            resolution_change_count: stats.uiResolutionChangeTimes,
+            idr_count: stats.uiIDRCorrectNum,
+            lost_idr_count: stats.uiIDRLostNum,
+            concealed_frame_count: stats.uiEcFrameNum,
+            concealed_idr_count: stats.uiEcIDRNum,
+            average_concealed_ratio: stats.uiAvgEcRatio,
+            average_propagated_concealed_ratio: stats.uiAvgEcPropRatio,
+            frozen_idr_count: stats.uiFreezingIDRNum,
+            frozen_frame_count: stats.uiFreezingNonIDRNum,
+            average_luma_qp: if stats.iAvgLumaQp < 0 { None } else { Some(stats.iAvgLumaQp) },
+            invalid_sps_count: stats.iSpsReportErrorNum as u32,
+            invalid_pps_count: stats.iPpsReportErrorNum as u32,
+            missing_sps_count: stats.iSpsNoExistNalNum as u32,
+            missing_pps_count: stats.iPpsNoExistNalNum as u32,
+            profile_idc: stats.uiProfile,
+            level_idc: stats.uiLevel,
+        }
+    }
+}
+
+/// Color and aspect ratio information signaled in the VUI of a stream's sequence parameter set.
+///
+/// Fields are `None` if the stream doesn't signal them, or uses a value without a matching variant.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct VuiInfo {
+    /// Chromaticity coordinates of the source primaries.
+    pub color_primaries: Option<ColorPrimaries>,
+    /// Transfer characteristics (gamma).
+    pub transfer_characteristics: Option<TransferCharacteristics>,
+    /// Matrix coefficients for RGB↔YCbCr conversion.
+    pub matrix_coefficients: Option<MatrixCoefficients>,
+    /// True for full range (0-255), false for limited range (16-235).
+    pub full_range: bool,
+    /// Shape of a pixel.
+    pub sample_aspect_ratio: Option<SampleAspectRatio>,
+}
+
+impl VuiInfo {
+    /// The color space to convert the stream to RGB with.
+    ///
+    /// Streams without matrix coefficients are treated as BT.601.
+    pub const fn color_space(&self) -> ColorSpace {
+        let color_matrix = match self.matrix_coefficients {
+            Some(matrix_coefficients) => matrix_coefficients.color_matrix(),
+            None => ColorMatrix::Bt601,
+        };
+
+        ColorSpace::new(color_matrix, self.full_range)
+    }
+}
+
+/// Metadata of a decoded frame, e.g., to send loss and long term reference (LTR) feedback to an encoder.
+///
+/// Values are `None` if the decoder can't report them. This includes frames held back to be output in display order,
+/// e.g., of streams using B-frames, and frames returned by [`Decoder::flush_remaining()`], since OpenH264 only reports
+/// the metadata of the access unit decoded last.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// The `frame_num` of the frame's slice headers.
+    pub frame_num: Option<u32>,
+    /// The `idr_pic_id` of the last IDR frame.
+    pub idr_pic_id: Option<u32>,
+    /// Temporal layer of the frame, `None` if the access unit contained no slices.
+    ///
+    /// Only signaled in SVC NAL header extensions, plain AVC streams always report `0`.
+    pub temporal_id: Option<u8>,
+    /// True if the frame is used as reference by later frames.
+    pub is_reference: bool,
+    /// The `frame_num` of the frame marked as long term reference, if the access unit marked one.
+    pub ltr_marked_frame_num: Option<u32>,
+}
+
 /// Frame returned by the [`Decoder`] and provides safe data access.
 #[derive(Debug)]
 pub struct DecodedYUV<'a> {
     info: SSysMEMBuffer,
     timestamp: Timestamp,
+    vui: VuiInfo,
+    concealed: bool,
+    frame_info: FrameInfo,
 
     y: &'a [u8],
     u: &'a [u8],
@@ -479,7 +798,13 @@ impl DecodedYUV<'_> {
     ///
     /// This can soft-fail (return `None`) because we might still have gotten `null` pointers from
     /// OpenH264 despite it not having returned an error on decode.
-    const unsafe fn from_raw_open264_ptrs(dst: &[*mut u8; 3], buffer_info: &TagBufferInfo) -> Option<Self> {
+    const unsafe fn from_raw_open264_ptrs(
+        dst: &[*mut u8; 3],
+        buffer_info: &TagBufferInfo,
+        vui: VuiInfo,
+        concealed: bool,
+        frame_info: FrameInfo,
+    ) -> Option<Self> {
         unsafe {
             let info = buffer_info.UsrData.sSystemBuffer;
             let timestamp = Timestamp::from_millis(buffer_info.uiInBsTimeStamp); // TODO: Is this the right one?
@@ -497,6 +822,9 @@ impl DecodedYUV<'_> {
                 Some(Self {
                     info,
                     timestamp,
+                    vui,
+                    concealed,
+                    frame_info,
                     y,
                     u,
                     v,
@@ -519,6 +847,69 @@ impl DecodedYUV<'_> {
         self.timestamp
     }
 
+    /// Color and aspect ratio information of the most recent sequence parameter set the decoder has seen.
+    #[must_use]
+    pub const fn vui(&self) -> VuiInfo {
+        self.vui
+    }
+
+    /// True if the frame was damaged, and the decoder concealed the damage as configured via
+    /// [`DecoderConfig::error_concealment()`].
+    #[must_use]
+    pub const fn is_concealed(&self) -> bool {
+        self.concealed
+    }
+
+    /// Frame number, reference and LTR information reported by the decoder.
+    #[must_use]
+    pub const fn frame_info(&self) -> FrameInfo {
+        self.frame_info
+    }
+
+    /// Copies the frame into an [`OwnedYUVFrame`], which can outlive the next call to [`Decoder::decode()`].
+    ///
+    /// Planes are packed, i.e., padding between rows is dropped.
+    #[must_use]
+    pub fn to_owned_frame(&self) -> OwnedYUVFrame {
+        self.copy_into(Vec::new(), None)
+    }
+
+    /// Like [`to_owned_frame()`](Self::to_owned_frame), but reuses a buffer from `pool` if one is available, and returns it
+    /// there once the frame is dropped.
+    #[must_use]
+    pub fn to_owned_frame_with_pool(&self, pool: &FramePool) -> OwnedYUVFrame {
+        self.copy_into(pool.take(), Some(pool.clone()))
+    }
+
+    fn copy_into(&self, mut buffer: Vec<u8>, pool: Option<FramePool>) -> OwnedYUVFrame {
+        let (width, height) = self.dimensions();
+        let (width_uv, height_uv) = (width.div_ceil(2), height.div_ceil(2));
+        let strides = self.strides();
+
+        buffer.clear();
+        buffer.reserve(width * height + 2 * width_uv * height_uv);
+
+        for row in self.y.chunks(strides.0).take(height) {
+            buffer.extend_from_slice(&row[..width]);
+        }
+        for row in self.u.chunks(strides.1).take(height_uv) {
+            buffer.extend_from_slice(&row[..width_uv]);
+        }
+        for row in self.v.chunks(strides.2).take(height_uv) {
+            buffer.extend_from_slice(&row[..width_uv]);
+        }
+
+        OwnedYUVFrame {
+            buffer,
+            dimensions: (width, height),
+            timestamp: self.timestamp,
+            vui: self.vui,
+            concealed: self.concealed,
+            frame_info: self.frame_info,
+            pool,
+        }
+    }
+
     /// Cut the YUV buffer into vertical sections.
     ///
     /// The slices do not overlap. If N does not divide the buffer, then the last YUVSlice has fewer pixel rows.
@@ -544,70 +935,112 @@ impl DecodedYUV<'_> {
     }
 
     // TODO: Ideally we'd like to move these out into a converter in `formats`.
-    /// Writes the image into a byte buffer of size `w*h*3`.
+    /// Writes the image into a byte buffer of size `w*h*3`, treating it as BT.601 limited range.
     ///
     /// # Panics
     ///
     /// Panics if the target image dimension don't match the configured format.
-    #[allow(clippy::unnecessary_cast)]
     pub fn write_rgb8(&self, target: &mut [u8]) {
-        let dim = self.dimensions();
-        let strides = self.strides();
-        let wanted = dim.0 * dim.1 * 3;
+        self.write_rgb8_with_color_space(target, ColorSpace::default());
+    }
 
-        // This needs some love, and better architecture.
-        assert_eq!(self.info.iFormat, videoFormatI420 as i32);
-        assert_eq!(
-            target.len(),
-            wanted,
-            "Target RGB8 array does not match image dimensions. Wanted: {} * {} * 3 = {}, got {}",
-            dim.0,
-            dim.1,
-            wanted,
-            target.len()
-        );
+    /// Writes the image into a byte buffer of size `w*h*3`, converting with the color space signaled by the stream.
+    ///
+    /// See [`VuiInfo::color_space()`] for how the color space is picked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target image dimension don't match the configured format.
+    pub fn write_rgb8_auto(&self, target: &mut [u8]) {
+        self.write_rgb8_with_color_space(target, self.vui.color_space());
+    }
 
-        // for f32x8 math, image needs to:
-        //   - have a width divisible by 8
-        //   - have at least two rows
-        if dim.0 % 8 == 0 && dim.1 >= 2 {
-            write_rgb8_f32x8(self.y, self.u, self.v, dim, strides, target);
-        } else {
-            write_rgb8_scalar(self.y, self.u, self.v, dim, strides, target);
-        }
+    /// Writes the image into a byte buffer of size `w*h*3`, converting from the given color space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target image dimension don't match the configured format.
+    pub fn write_rgb8_with_color_space(&self, target: &mut [u8], color_space: ColorSpace) {
+        self.assert_target_len(target, 3, "RGB8");
+
+        yuv2rgb::write_rgb8(self.y, self.u, self.v, self.dimensions(), self.strides(), color_space, target);
+    }
+
+    /// Writes the image into a byte buffer of size `w*h*3` like [`write_rgb8_with_color_space()`](Self::write_rgb8_with_color_space),
+    /// splitting the work across up to `num_threads` threads.
+    ///
+    /// Worth it for large frames, e.g., 4K previews; small ones are faster on a single thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target image dimension don't match the configured format.
+    pub fn write_rgb8_par(&self, target: &mut [u8], color_space: ColorSpace, num_threads: usize) {
+        self.assert_target_len(target, 3, "RGB8");
+
+        let (dim, strides) = (self.dimensions(), self.strides());
+        yuv2rgb::write_rgb8_par(self.y, self.u, self.v, dim, strides, color_space, num_threads, target);
     }
 
     // TODO: Ideally we'd like to move these out into a converter in `formats`.
-    /// Writes the image into a byte buffer of size `w*h*4`.
+    /// Writes the image into a byte buffer of size `w*h*4`, treating it as BT.601 limited range.
     ///
     /// # Panics
     ///
     /// Panics if the target image dimension don't match the configured format.
-    #[allow(clippy::unnecessary_cast)]
     pub fn write_rgba8(&self, target: &mut [u8]) {
+        self.write_rgba8_with_color_space(target, ColorSpace::default());
+    }
+
+    /// Writes the image into a byte buffer of size `w*h*4`, converting with the color space signaled by the stream.
+    ///
+    /// See [`VuiInfo::color_space()`] for how the color space is picked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target image dimension don't match the configured format.
+    pub fn write_rgba8_auto(&self, target: &mut [u8]) {
+        self.write_rgba8_with_color_space(target, self.vui.color_space());
+    }
+
+    /// Writes the image into a byte buffer of size `w*h*4`, converting from the given color space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target image dimension don't match the configured format.
+    pub fn write_rgba8_with_color_space(&self, target: &mut [u8], color_space: ColorSpace) {
+        self.assert_target_len(target, 4, "RGBA8");
+
+        yuv2rgb::write_rgba8(self.y, self.u, self.v, self.dimensions(), self.strides(), color_space, target);
+    }
+
+    /// Writes the image into a byte buffer of size `w*h*4` like [`write_rgba8_with_color_space()`](Self::write_rgba8_with_color_space),
+    /// splitting the work across up to `num_threads` threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target image dimension don't match the configured format.
+    pub fn write_rgba8_par(&self, target: &mut [u8], color_space: ColorSpace, num_threads: usize) {
+        self.assert_target_len(target, 4, "RGBA8");
+
+        let (dim, strides) = (self.dimensions(), self.strides());
+        yuv2rgb::write_rgba8_par(self.y, self.u, self.v, dim, strides, color_space, num_threads, target);
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    fn assert_target_len(&self, target: &[u8], pixel_len: usize, format: &str) {
         let dim = self.dimensions();
-        let strides = self.strides();
-        let wanted = dim.0 * dim.1 * 4;
+        let wanted = dim.0 * dim.1 * pixel_len;
 
         // This needs some love, and better architecture.
         assert_eq!(self.info.iFormat, videoFormatI420 as i32);
         assert_eq!(
             target.len(),
             wanted,
-            "Target RGBA8 array does not match image dimensions. Wanted: {} * {} * 4 = {}, got {}",
+            "Target {format} array does not match image dimensions. Wanted: {} * {} * {pixel_len} = {wanted}, got {}",
             dim.0,
             dim.1,
-            wanted,
             target.len()
         );
-        // for f32x8 math, image needs to:
-        //   - have a width divisible by 8
-        //   - have at least two rows
-        if dim.0 % 8 == 0 && dim.1 >= 2 {
-            write_rgba8_f32x8(self.y, self.u, self.v, dim, strides, target);
-        } else {
-            write_rgba8_scalar(self.y, self.u, self.v, dim, strides, target);
-        }
     }
 }
 
@@ -647,6 +1080,184 @@ impl YUVSource for DecodedYUV<'_> {
     }
 }
 
+/// Decoded frame owning its pixel data, as returned by [`DecodedYUV::to_owned_frame()`].
+///
+/// Unlike [`DecodedYUV`] it does not borrow the [`Decoder`], so it can be queued, reordered, or sent to another
+/// thread. Convert it to RGB through [`YUVSource`].
+#[derive(Debug)]
+pub struct OwnedYUVFrame {
+    buffer: Vec<u8>,
+    dimensions: (usize, usize),
+    timestamp: Timestamp,
+    vui: VuiInfo,
+    concealed: bool,
+    frame_info: FrameInfo,
+    pool: Option<FramePool>,
+}
+
+impl OwnedYUVFrame {
+    /// Timestamp of this frame in milliseconds(?) with respect to the video stream.
+    #[must_use]
+    pub const fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Color and aspect ratio information of the stream this frame was decoded from.
+    #[must_use]
+    pub const fn vui(&self) -> VuiInfo {
+        self.vui
+    }
+
+    /// True if the decoder concealed damage in this frame, see [`DecodedYUV::is_concealed()`].
+    #[must_use]
+    pub const fn is_concealed(&self) -> bool {
+        self.concealed
+    }
+
+    /// Frame number, reference and LTR information, see [`DecodedYUV::frame_info()`].
+    #[must_use]
+    pub const fn frame_info(&self) -> FrameInfo {
+        self.frame_info
+    }
+
+    const fn dimensions_uv(&self) -> (usize, usize) {
+        (self.dimensions.0.div_ceil(2), self.dimensions.1.div_ceil(2))
+    }
+}
+
+impl Clone for OwnedYUVFrame {
+    /// Clones the frame, taking its buffer from the same pool (if any).
+    fn clone(&self) -> Self {
+        let mut buffer = self.pool.as_ref().map(FramePool::take).unwrap_or_default();
+        buffer.clear();
+        buffer.extend_from_slice(&self.buffer);
+
+        Self {
+            buffer,
+            dimensions: self.dimensions,
+            timestamp: self.timestamp,
+            vui: self.vui,
+            concealed: self.concealed,
+            frame_info: self.frame_info,
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl Drop for OwnedYUVFrame {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            pool.put(std::mem::take(&mut self.buffer));
+        }
+    }
+}
+
+impl YUVSource for OwnedYUVFrame {
+    fn dimensions(&self) -> (usize, usize) {
+        self.dimensions
+    }
+
+    fn strides(&self) -> (usize, usize, usize) {
+        let (width_uv, _) = self.dimensions_uv();
+        (self.dimensions.0, width_uv, width_uv)
+    }
+
+    fn y(&self) -> &[u8] {
+        &self.buffer[..self.dimensions.0 * self.dimensions.1]
+    }
+
+    fn u(&self) -> &[u8] {
+        let (width_uv, height_uv) = self.dimensions_uv();
+        let start = self.dimensions.0 * self.dimensions.1;
+        &self.buffer[start..start + width_uv * height_uv]
+    }
+
+    fn v(&self) -> &[u8] {
+        let (width_uv, height_uv) = self.dimensions_uv();
+        let start = self.dimensions.0 * self.dimensions.1 + width_uv * height_uv;
+        &self.buffer[start..start + width_uv * height_uv]
+    }
+}
+
+/// Recycles the buffers of [`OwnedYUVFrame`]s, so steady-state playback does not allocate.
+///
+/// Frames created with [`DecodedYUV::to_owned_frame_with_pool()`] return their buffer here when dropped. Clones of a
+/// pool share the same buffers, and can be sent to other threads.
+///
+/// A pool from [`new()`](Self::new) keeps every returned buffer, so it grows to the most frames alive at once. Use
+/// [`with_capacity()`](Self::with_capacity) to bound it.
+///
+/// # Example
+///
+/// ```rust
+/// use openh264::decoder::{Decoder, FramePool};
+/// # use openh264::Error;
+///
+/// # fn main() -> Result<(), Error> {
+/// let h264_in = include_bytes!("../tests/data/multi_512x512.h264");
+/// let mut decoder = Decoder::new()?;
+/// let pool = FramePool::new();
+/// let mut frames = Vec::new();
+///
+/// for packet in openh264::nal_units(h264_in) {
+///     if let Ok(Some(yuv)) = decoder.decode(packet) {
+///         frames.push(yuv.to_owned_frame_with_pool(&pool));
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct FramePool {
+    buffers: Arc<Mutex<Vec<Vec<u8>>>>,
+    capacity: usize,
+}
+
+impl FramePool {
+    /// Creates an empty pool without a limit on the buffers it keeps.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_capacity(usize::MAX)
+    }
+
+    /// Creates an empty pool keeping at most `capacity` buffers, further ones are freed when their frame is dropped.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffers: Arc::default(),
+            capacity,
+        }
+    }
+
+    /// Number of buffers ready to be reused.
+    #[must_use]
+    pub fn available(&self) -> usize {
+        self.buffers.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    fn take(&self) -> Vec<u8> {
+        self.buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+            .unwrap_or_default()
+    }
+
+    fn put(&self, buffer: Vec<u8>) {
+        let mut buffers = self.buffers.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if buffers.len() < self.capacity {
+            buffers.push(buffer);
+        }
+    }
+}
+
+impl Default for FramePool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use openh264_sys2::SSysMEMBuffer;
@@ -656,7 +1267,7 @@ mod test {
         formats::{YUVSlices, YUVSource},
     };
 
-    use super::DecodedYUV;
+    use super::{DecodedYUV, FrameInfo, FramePool, OwnedYUVFrame, VuiInfo};
 
     /// Create YUV420 plane buffers.
     ///
@@ -692,6 +1303,9 @@ mod test {
                     iStride: [$y_stride as i32, $y_stride / 2 as i32],
                 },
                 timestamp: Timestamp::ZERO,
+                vui: VuiInfo::default(),
+                concealed: false,
+                frame_info: FrameInfo::default(),
                 y: $y,
                 u: $u,
                 v: $v,
@@ -733,4 +1347,63 @@ mod test {
         assert_eq!(buf.v().len(), v_plane.len());
         assert_eq!(buf.v(), v_plane);
     }
+
+    #[test]
+    fn to_owned_frame_packs_planes() {
+        let (y, u, v) = yuv420_planes!(y_stride: 12, height: 4);
+        let buf = decoded_yuv420!(y_stride: 12, dim: (8, 4), &y, &u, &v);
+
+        let frame = buf.to_owned_frame();
+
+        assert_eq!(frame.dimensions(), (8, 4));
+        assert_eq!(frame.strides(), (8, 4, 4));
+        assert_eq!(frame.y(), [&y[0..8], &y[12..20], &y[24..32], &y[36..44]].concat());
+        assert_eq!(frame.u(), [&u[0..4], &u[6..10]].concat());
+        assert_eq!(frame.v(), [&v[0..4], &v[6..10]].concat());
+        assert_eq!(frame.timestamp(), buf.timestamp());
+    }
+
+    #[test]
+    fn to_owned_frame_with_pool_reuses_buffers() {
+        fn assert_send<T: Send>() {}
+        assert_send::<OwnedYUVFrame>();
+        assert_send::<FramePool>();
+
+        let (y, u, v) = yuv420_planes!(y_stride: 8, height: 8);
+        let buf = decoded_yuv420!(y_stride: 8, dim: (8, 8), &y, &u, &v);
+        let pool = FramePool::new();
+
+        let frame = buf.to_owned_frame_with_pool(&pool);
+        let ptr = frame.y().as_ptr();
+        assert_eq!(pool.available(), 0);
+
+        drop(frame);
+        assert_eq!(pool.available(), 1);
+
+        let frame = buf.to_owned_frame_with_pool(&pool);
+        assert_eq!(frame.y().as_ptr(), ptr);
+        assert_eq!(frame.y(), y);
+        assert_eq!(pool.available(), 0);
+
+        let copy = frame.clone();
+        drop(frame);
+        drop(copy);
+        assert_eq!(pool.available(), 2);
+    }
+
+    #[test]
+    fn frame_pool_with_capacity_drops_extra_buffers() {
+        let (y, u, v) = yuv420_planes!(y_stride: 8, height: 8);
+        let buf = decoded_yuv420!(y_stride: 8, dim: (8, 8), &y, &u, &v);
+        let pool = FramePool::with_capacity(1);
+
+        let frames = [buf.to_owned_frame_with_pool(&pool), buf.to_owned_frame_with_pool(&pool)];
+        drop(frames);
+        assert_eq!(pool.available(), 1);
+
+        let frame = buf.to_owned_frame_with_pool(&pool);
+        assert_eq!(pool.available(), 0);
+        drop(frame);
+        assert_eq!(pool.available(), 1);
+    }
 }
@@ -1,16 +1,24 @@
 //! Converts YUV / RGB images to NAL packets.
 
 use crate::error::NativeErrorExt;
-use crate::formats::YUVSource;
+use crate::formats::{ColorMatrix, ColorSpace, EncoderInput, YUVBuffer, YUVSource};
 use crate::{Error, OpenH264API, Timestamp};
 use openh264_sys2::{
-    API, DEBLOCKING_IDC_0, ELevelIdc, ENCODER_OPTION, ENCODER_OPTION_DATAFORMAT, ENCODER_OPTION_SVC_ENCODE_PARAM_EXT,
-    ENCODER_OPTION_TRACE_LEVEL, EProfileIdc, EUsageType, EVideoFormatType, ISVCEncoder, ISVCEncoderVtbl, RC_MODES, SEncParamBase,
-    SEncParamExt, SFrameBSInfo, SLayerBSInfo, SM_SINGLE_SLICE, SM_SIZELIMITED_SLICE, SSourcePicture, VIDEO_CODING_LAYER,
-    WELS_LOG_DETAIL, WELS_LOG_QUIET, videoFormatI420,
+    API, DEBLOCKING_IDC_0, DEBLOCKING_IDC_1, DEBLOCKING_IDC_2, ELevelIdc, ENCODER_LTR_MARKING_FEEDBACK,
+    ENCODER_LTR_RECOVERY_REQUEST, ENCODER_OPTION, ENCODER_OPTION_BITRATE, ENCODER_OPTION_DATAFORMAT, ENCODER_OPTION_FRAME_RATE,
+    ENCODER_OPTION_GET_STATISTICS, ENCODER_OPTION_MAX_BITRATE, ENCODER_OPTION_STATISTICS_LOG_INTERVAL,
+    ENCODER_OPTION_SVC_ENCODE_PARAM_EXT, ENCODER_OPTION_TRACE_LEVEL, EProfileIdc, ESampleAspectRatio, EUsageType,
+    EVideoFormatType, ISVCEncoder, ISVCEncoderVtbl, LAYER_NUM, LTR_RECOVERY_REQUEST, MAX_SLICES_NUM_TMP, RC_MODES, SBitrateInfo,
+    SEncParamBase, SEncParamExt, SEncoderStatistics, SFrameBSInfo, SLTRMarkingFeedback, SLTRRecoverRequest, SLayerBSInfo,
+    SM_FIXEDSLCNUM_SLICE, SM_RASTER_SLICE, SM_SINGLE_SLICE, SM_SIZELIMITED_SLICE, SPATIAL_LAYER_0, SPATIAL_LAYER_ALL,
+    SSliceArgument, SSourcePicture, VIDEO_CODING_LAYER, WELS_LOG_DETAIL, WELS_LOG_QUIET, videoFormatI420,
 };
-use std::os::raw::{c_int, c_uchar, c_void};
+use std::os::raw::{c_int, c_uchar, c_uint, c_void};
 use std::ptr::{addr_of_mut, from_mut, null, null_mut};
+use std::time::Duration;
+
+#[cfg(feature = "log")]
+use openh264_sys2::{ENCODER_OPTION_TRACE_CALLBACK, ENCODER_OPTION_TRACE_CALLBACK_CONTEXT, WelsTraceCallback};
 
 /// Convenience wrapper with guaranteed function pointers for easy access.
 ///
@@ -240,6 +248,33 @@ impl Profile {
             Self::ScalableHigh => openh264_sys2::PRO_SCALABLE_HIGH,
         }
     }
+
+    const fn supports_cabac(self) -> bool {
+        !matches!(self, Self::Baseline | Self::Extended | Self::CAVLC444 | Self::ScalableBaseline)
+    }
+}
+
+/// Entropy coding method used for the encoded bitstream.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntropyCoding {
+    /// Context-adaptive variable-length coding, supported by all profiles.
+    #[default]
+    Cavlc,
+    /// Context-adaptive binary arithmetic coding, smaller but slower to encode and decode.
+    ///
+    /// Not supported by the [`Baseline`](Profile::Baseline), [`Extended`](Profile::Extended),
+    /// [`CAVLC444`](Profile::CAVLC444) and [`ScalableBaseline`](Profile::ScalableBaseline) profiles. If no profile is set,
+    /// OpenH264 picks [`High`](Profile::High).
+    Cabac,
+}
+
+impl EntropyCoding {
+    const fn to_c(self) -> c_int {
+        match self {
+            Self::Cavlc => 0,
+            Self::Cabac => 1,
+        }
+    }
 }
 
 /// H.264 encoding levels with their corresponding capabilities.
@@ -343,6 +378,83 @@ impl Complexity {
     }
 }
 
+/// How frames are split into slices.
+///
+/// Slices are encoded independently of each other, which lets OpenH264 encode them in parallel, and lets a lost packet
+/// only affect part of a frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SliceMode {
+    /// One slice per frame.
+    #[default]
+    Single,
+    /// Fixed number of slices per frame, `0` uses one slice per available CPU core.
+    ///
+    /// Slices are spread across encoder threads, see [`EncoderConfig::num_threads()`]. A frame can have at most
+    /// 35 slices, larger counts make encoding fail.
+    Fixed(u32),
+    /// Slices of the given number of macroblocks each, the last slice taking the remainder. `0` uses one macroblock row per slice.
+    ///
+    /// A frame can have at most 35 slices, settings resulting in more make encoding fail.
+    Raster(u32),
+    /// Slices are cut dynamically so they don't exceed the given number of bytes.
+    SizeLimited(u32),
+}
+
+impl SliceMode {
+    /// Maps the mode for a frame of the given size in pixels, rejecting settings that need too many slices.
+    fn to_c(self, width: u32, height: u32) -> Result<SSliceArgument, Error> {
+        let (mb_width, mb_height) = (width.div_ceil(16), height.div_ceil(16));
+        let mut rval = SSliceArgument {
+            uiSliceNum: 1,
+            ..Default::default()
+        };
+
+        match self {
+            Self::Single => rval.uiSliceMode = SM_SINGLE_SLICE,
+            Self::Fixed(num) => {
+                let num = match num {
+                    0 => std::thread::available_parallelism()
+                        .map_or(1, |x| x.get() as u32)
+                        .min(MAX_SLICES_NUM_TMP),
+                    _ => num,
+                };
+
+                if num > MAX_SLICES_NUM_TMP {
+                    return Err(Error::msg_string(format!(
+                        "{num} slices exceed the maximum of {MAX_SLICES_NUM_TMP}"
+                    )));
+                }
+
+                rval.uiSliceMode = SM_FIXEDSLCNUM_SLICE;
+                rval.uiSliceNum = num;
+            }
+            Self::Raster(mbs) => {
+                let mbs = match mbs {
+                    0 => mb_width,
+                    _ => mbs,
+                };
+                let num = (mb_width * mb_height).div_ceil(mbs);
+
+                if num > MAX_SLICES_NUM_TMP {
+                    return Err(Error::msg_string(format!(
+                        "{mbs} macroblocks per slice need {num} slices for {width}x{height}, exceeding the maximum of {MAX_SLICES_NUM_TMP}"
+                    )));
+                }
+
+                // OpenH264 stops at the first slice reaching the frame's end and trims it.
+                rval.uiSliceMode = SM_RASTER_SLICE;
+                rval.uiSliceMbNum = [mbs; MAX_SLICES_NUM_TMP as usize];
+            }
+            Self::SizeLimited(bytes) => {
+                rval.uiSliceMode = SM_SIZELIMITED_SLICE;
+                rval.uiSliceSizeConstraint = bytes;
+            }
+        }
+
+        Ok(rval)
+    }
+}
+
 /// Quantization parameter range to control the degree of compression.
 ///
 /// This can be used to control the balance between size and video quality.
@@ -376,6 +488,66 @@ impl Default for QpRange {
     }
 }
 
+/// Where the deblocking filter is applied.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeblockingMode {
+    /// Filter all macroblock edges.
+    #[default]
+    On,
+    /// Disable the deblocking filter.
+    Off,
+    /// Filter all macroblock edges except those on slice boundaries.
+    OffAcrossSlices,
+}
+
+impl DeblockingMode {
+    const fn to_c(self) -> c_int {
+        match self {
+            Self::On => DEBLOCKING_IDC_0,
+            Self::Off => DEBLOCKING_IDC_1,
+            Self::OffAcrossSlices => DEBLOCKING_IDC_2,
+        }
+    }
+}
+
+/// Configures the deblocking filter, which smooths block edges in decoded frames.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Deblocking {
+    mode: DeblockingMode,
+    alpha_offset: i8,
+    beta_offset: i8,
+}
+
+impl Deblocking {
+    /// Creates a new deblocking config with the given mode and zero offsets.
+    #[must_use]
+    pub const fn new(mode: DeblockingMode) -> Self {
+        Self {
+            mode,
+            alpha_offset: 0,
+            beta_offset: 0,
+        }
+    }
+
+    /// Adjusts the filter strength.
+    ///
+    /// Valid values are between -6 and 6. Positive values filter more edges (`alpha`) or filter them more
+    /// strongly (`beta`), negative values preserve more detail.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` or `beta` are out of range.
+    #[must_use]
+    pub const fn offsets(mut self, alpha: i8, beta: i8) -> Self {
+        assert!(alpha >= -6 && alpha <= 6, "deblocking alpha offset out of range (-6..=6)");
+        assert!(beta >= -6 && beta <= 6, "deblocking beta offset out of range (-6..=6)");
+
+        self.alpha_offset = alpha;
+        self.beta_offset = beta;
+        self
+    }
+}
+
 /// A period in frames after which a new I-Frame is generated.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct IntraFramePeriod(u32);
@@ -399,6 +571,65 @@ impl IntraFramePeriod {
     }
 }
 
+/// Maximum number of spatial layers supported by OpenH264.
+pub const MAX_SPATIAL_LAYERS: usize = openh264_sys2::MAX_SPATIAL_LAYER_NUM as usize;
+
+/// Describes a single spatial layer produced by the [`Encoder`].
+///
+/// All spatial layers are derived from the same input frame, which OpenH264 downscales as needed.
+/// Layers must be added to [`EncoderConfig::spatial_layer()`] in ascending order of resolution,
+/// and no layer may be larger than the frames passed to [`Encoder::encode()`].
+#[derive(Debug, Clone, Copy)]
+#[must_use]
+pub struct SpatialLayer {
+    width: u32,
+    height: u32,
+    bitrate: BitRate,
+    max_bitrate: Option<BitRate>,
+    frame_rate: Option<FrameRate>,
+    profile: Option<Profile>,
+    level: Option<Level>,
+}
+
+impl SpatialLayer {
+    /// Creates a new layer with the given resolution and bitrate.
+    pub const fn new(width: u32, height: u32, bitrate: BitRate) -> Self {
+        Self {
+            width,
+            height,
+            bitrate,
+            max_bitrate: None,
+            frame_rate: None,
+            profile: None,
+            level: None,
+        }
+    }
+
+    /// Sets the maximum bitrate of this layer (defaults to the layer bitrate).
+    pub const fn max_bitrate(mut self, value: BitRate) -> Self {
+        self.max_bitrate = Some(value);
+        self
+    }
+
+    /// Sets the frame rate of this layer (defaults to [`EncoderConfig::max_frame_rate()`]).
+    pub const fn frame_rate(mut self, value: FrameRate) -> Self {
+        self.frame_rate = Some(value);
+        self
+    }
+
+    /// Sets the encoding profile of this layer (defaults to [`EncoderConfig::profile()`]).
+    pub const fn profile(mut self, profile: Profile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Sets the encoding profile level of this layer (defaults to [`EncoderConfig::level()`]).
+    pub const fn level(mut self, level: Level) -> Self {
+        self.level = Some(level);
+        self
+    }
+}
+
 // =============================================================================
 // VUI (Video Usability Information) Parameters
 // =============================================================================
@@ -431,6 +662,21 @@ pub enum ColorPrimaries {
 }
 
 impl ColorPrimaries {
+    /// Parses a raw VUI value, `None` for reserved or unsupported ones.
+    pub(crate) const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Bt709),
+            2 => Some(Self::Unspecified),
+            4 => Some(Self::Bt470M),
+            5 => Some(Self::Bt470BG),
+            6 => Some(Self::Smpte170M),
+            7 => Some(Self::Smpte240M),
+            8 => Some(Self::Film),
+            9 => Some(Self::Bt2020),
+            _ => None,
+        }
+    }
+
     /// Get the raw u8 value for the VUI colour_primaries field.
     #[must_use]
     pub const fn as_u8(self) -> u8 {
@@ -472,6 +718,25 @@ pub enum TransferCharacteristics {
 }
 
 impl TransferCharacteristics {
+    /// Parses a raw VUI value, `None` for reserved or unsupported ones.
+    pub(crate) const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Bt709),
+            2 => Some(Self::Unspecified),
+            4 => Some(Self::Bt470M),
+            5 => Some(Self::Bt470Bg),
+            6 => Some(Self::Smpte170M),
+            7 => Some(Self::Smpte240M),
+            8 => Some(Self::Linear),
+            13 => Some(Self::Srgb),
+            14 => Some(Self::Bt2020_10),
+            15 => Some(Self::Bt2020_12),
+            16 => Some(Self::Smpte2084),
+            18 => Some(Self::Hlg),
+            _ => None,
+        }
+    }
+
     /// Get the raw u8 value for the VUI transfer_characteristics field.
     #[must_use]
     pub const fn as_u8(self) -> u8 {
@@ -509,11 +774,127 @@ pub enum MatrixCoefficients {
 }
 
 impl MatrixCoefficients {
+    /// Parses a raw VUI value, `None` for reserved or unsupported ones.
+    pub(crate) const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Identity),
+            1 => Some(Self::Bt709),
+            2 => Some(Self::Unspecified),
+            4 => Some(Self::Fcc),
+            5 => Some(Self::Bt470Bg),
+            6 => Some(Self::Smpte170M),
+            7 => Some(Self::Smpte240M),
+            8 => Some(Self::Ycgco),
+            9 => Some(Self::Bt2020Ncl),
+            10 => Some(Self::Bt2020Cl),
+            _ => None,
+        }
+    }
+
     /// Get the raw u8 value for the VUI matrix_coefficients field.
     #[must_use]
     pub const fn as_u8(self) -> u8 {
         self as u8
     }
+
+    /// The conversion matrix closest to these coefficients.
+    ///
+    /// Coefficients without a matching [`ColorMatrix`] fall back to BT.601.
+    #[must_use]
+    pub const fn color_matrix(self) -> ColorMatrix {
+        match self {
+            Self::Bt709 => ColorMatrix::Bt709,
+            Self::Bt2020Ncl | Self::Bt2020Cl => ColorMatrix::Bt2020,
+            _ => ColorMatrix::Bt601,
+        }
+    }
+}
+
+/// H.264 sample aspect ratio values (ITU-T H.264 Table E-1).
+///
+/// Specifies the shape of a pixel as width:height, e.g., 1440x1080 content shown as 16:9 uses 4:3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SampleAspectRatio {
+    /// Unspecified
+    Unspecified,
+    /// 1:1 (square pixels)
+    #[default]
+    Sar1x1,
+    /// 12:11 (e.g., 720x576 4:3 PAL)
+    Sar12x11,
+    /// 10:11 (e.g., 720x480 4:3 NTSC)
+    Sar10x11,
+    /// 16:11 (e.g., 720x576 16:9 PAL)
+    Sar16x11,
+    /// 40:33 (e.g., 720x480 16:9 NTSC)
+    Sar40x33,
+    /// 24:11 (e.g., 352x576 4:3)
+    Sar24x11,
+    /// 20:11 (e.g., 352x480 4:3)
+    Sar20x11,
+    /// 32:11 (e.g., 352x576 16:9)
+    Sar32x11,
+    /// 80:33 (e.g., 352x480 16:9)
+    Sar80x33,
+    /// 18:11 (e.g., 480x576 4:3)
+    Sar18x11,
+    /// 15:11 (e.g., 480x480 4:3)
+    Sar15x11,
+    /// 64:33 (e.g., 540x576 16:9)
+    Sar64x33,
+    /// 160:99 (e.g., 528x480 16:9)
+    Sar160x99,
+    /// Any other ratio, stored as extended SAR.
+    Extended {
+        /// Relative pixel width.
+        width: u16,
+        /// Relative pixel height.
+        height: u16,
+    },
+}
+
+impl SampleAspectRatio {
+    /// Parses a VUI `aspect_ratio_idc`, with `width` and `height` only used for extended ratios.
+    pub(crate) const fn from_idc(idc: u8, width: u16, height: u16) -> Option<Self> {
+        match idc {
+            0 => Some(Self::Unspecified),
+            1 => Some(Self::Sar1x1),
+            2 => Some(Self::Sar12x11),
+            3 => Some(Self::Sar10x11),
+            4 => Some(Self::Sar16x11),
+            5 => Some(Self::Sar40x33),
+            6 => Some(Self::Sar24x11),
+            7 => Some(Self::Sar20x11),
+            8 => Some(Self::Sar32x11),
+            9 => Some(Self::Sar80x33),
+            10 => Some(Self::Sar18x11),
+            11 => Some(Self::Sar15x11),
+            12 => Some(Self::Sar64x33),
+            13 => Some(Self::Sar160x99),
+            255 => Some(Self::Extended { width, height }),
+            _ => None,
+        }
+    }
+
+    const fn to_c(self) -> (ESampleAspectRatio, u16, u16) {
+        match self {
+            Self::Unspecified => (openh264_sys2::ASP_UNSPECIFIED, 0, 0),
+            Self::Sar1x1 => (openh264_sys2::ASP_1x1, 0, 0),
+            Self::Sar12x11 => (openh264_sys2::ASP_12x11, 0, 0),
+            Self::Sar10x11 => (openh264_sys2::ASP_10x11, 0, 0),
+            Self::Sar16x11 => (openh264_sys2::ASP_16x11, 0, 0),
+            Self::Sar40x33 => (openh264_sys2::ASP_40x33, 0, 0),
+            Self::Sar24x11 => (openh264_sys2::ASP_24x11, 0, 0),
+            Self::Sar20x11 => (openh264_sys2::ASP_20x11, 0, 0),
+            Self::Sar32x11 => (openh264_sys2::ASP_32x11, 0, 0),
+            Self::Sar80x33 => (openh264_sys2::ASP_80x33, 0, 0),
+            Self::Sar18x11 => (openh264_sys2::ASP_18x11, 0, 0),
+            Self::Sar15x11 => (openh264_sys2::ASP_15x11, 0, 0),
+            Self::Sar64x33 => (openh264_sys2::ASP_64x33, 0, 0),
+            Self::Sar160x99 => (openh264_sys2::ASP_160x99, 0, 0),
+            Self::Extended { width, height } => (openh264_sys2::ASP_EXT_SAR, width, height),
+        }
+    }
 }
 
 /// H.264 VUI configuration for signaling color space to decoders.
@@ -540,6 +921,8 @@ pub struct VuiConfig {
     matrix_coefficients: MatrixCoefficients,
     /// True for full range (0-255), false for limited range (16-235)
     full_range: bool,
+    /// Shape of a pixel, not signaled if `None`
+    sample_aspect_ratio: Option<SampleAspectRatio>,
 }
 
 impl VuiConfig {
@@ -557,6 +940,7 @@ impl VuiConfig {
             transfer_characteristics: TransferCharacteristics::Bt709,
             matrix_coefficients: MatrixCoefficients::Bt709,
             full_range: false,
+            sample_aspect_ratio: None,
         }
     }
 
@@ -567,6 +951,7 @@ impl VuiConfig {
             transfer_characteristics: TransferCharacteristics::Bt709,
             matrix_coefficients: MatrixCoefficients::Bt709,
             full_range: true,
+            sample_aspect_ratio: None,
         }
     }
 
@@ -577,6 +962,7 @@ impl VuiConfig {
             transfer_characteristics: TransferCharacteristics::Smpte170M,
             matrix_coefficients: MatrixCoefficients::Smpte170M,
             full_range: false,
+            sample_aspect_ratio: None,
         }
     }
 
@@ -589,6 +975,7 @@ impl VuiConfig {
             transfer_characteristics: TransferCharacteristics::Srgb,
             matrix_coefficients: MatrixCoefficients::Bt709,
             full_range: true,
+            sample_aspect_ratio: None,
         }
     }
 
@@ -599,6 +986,7 @@ impl VuiConfig {
             transfer_characteristics: TransferCharacteristics::Bt2020_10,
             matrix_coefficients: MatrixCoefficients::Bt2020Ncl,
             full_range: false,
+            sample_aspect_ratio: None,
         }
     }
 
@@ -625,6 +1013,38 @@ impl VuiConfig {
         self.full_range = value;
         self
     }
+
+    /// Set the sample aspect ratio (not signaled by default).
+    pub const fn sample_aspect_ratio(mut self, value: SampleAspectRatio) -> Self {
+        self.sample_aspect_ratio = Some(value);
+        self
+    }
+
+    /// The color space RGB input should be converted with to match this configuration.
+    ///
+    /// Pass it to [`YUVBuffer::read_rgb8_with_color_space()`] before encoding.
+    pub const fn color_space(&self) -> ColorSpace {
+        ColorSpace::new(self.matrix_coefficients.color_matrix(), self.full_range)
+    }
+}
+
+/// Outcome of marking a long term reference frame, see [`Encoder::ltr_marking_feedback()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LtrMarking {
+    /// The LTR frame was received and decoded.
+    Success,
+    /// The LTR frame was lost or could not be decoded.
+    Failed,
+}
+
+impl LtrMarking {
+    /// The value of `SLTRMarkingFeedback::uiFeedbackType`.
+    const fn to_c(self) -> c_uint {
+        match self {
+            Self::Success => openh264_sys2::LTR_MARKING_SUCCESS as c_uint,
+            Self::Failed => openh264_sys2::LTR_MARKING_FAILED as c_uint,
+        }
+    }
 }
 
 /// Configuration for the [`Encoder`].
@@ -636,6 +1056,7 @@ impl VuiConfig {
 pub struct EncoderConfig {
     enable_skip_frame: bool,
     target_bitrate: BitRate,
+    max_bitrate: Option<BitRate>,
     enable_denoise: bool,
     debug: i32,
     data_format: EVideoFormatType,
@@ -644,17 +1065,27 @@ pub struct EncoderConfig {
     sps_pps_strategy: SpsPpsStrategy,
     multiple_thread_idc: u16,
     usage_type: UsageType,
-    max_slice_len: Option<u32>,
+    slice_mode: SliceMode,
     profile: Option<Profile>,
     level: Option<Level>,
     complexity: Complexity,
     qp: QpRange,
+    deblocking: Deblocking,
+    entropy_coding: EntropyCoding,
     scene_change_detect: bool,
     adaptive_quantization: bool,
     background_detection: bool,
     long_term_reference: bool,
+    ltr_ref_num: Option<u8>,
+    ltr_mark_period: u32,
     intra_frame_period: IntraFramePeriod,
     vui: Option<VuiConfig>,
+    spatial_layers: [Option<SpatialLayer>; MAX_SPATIAL_LAYERS],
+    simulcast: bool,
+    temporal_layers: u8,
+    prefix_nal: bool,
+    statistics_log_interval: Option<Duration>,
+    psnr: bool,
 }
 
 impl EncoderConfig {
@@ -663,6 +1094,7 @@ impl EncoderConfig {
         Self {
             enable_skip_frame: true,
             target_bitrate: BitRate::from_bps(120_000),
+            max_bitrate: None,
             enable_denoise: false,
             debug: 0,
             data_format: videoFormatI420,
@@ -671,17 +1103,27 @@ impl EncoderConfig {
             sps_pps_strategy: SpsPpsStrategy::ConstantId,
             multiple_thread_idc: 0,
             usage_type: UsageType::CameraVideoRealTime,
-            max_slice_len: None,
+            slice_mode: SliceMode::Single,
             profile: None,
             level: None,
             complexity: Complexity::Medium,
             qp: QpRange::new(0, 51),
+            deblocking: Deblocking::new(DeblockingMode::On),
+            entropy_coding: EntropyCoding::Cavlc,
             scene_change_detect: true,
             adaptive_quantization: true,
             background_detection: true,
             long_term_reference: false,
+            ltr_ref_num: None,
+            ltr_mark_period: 30,
             intra_frame_period: IntraFramePeriod::from_num_frames(0),
             vui: None,
+            spatial_layers: [None; MAX_SPATIAL_LAYERS],
+            simulcast: false,
+            temporal_layers: 1,
+            prefix_nal: false,
+            statistics_log_interval: None,
+            psnr: false,
         }
     }
 
@@ -691,7 +1133,17 @@ impl EncoderConfig {
         self
     }
 
+    /// Sets the maximum bit rate in bits per second (unspecified by default).
+    pub const fn max_bitrate(mut self, bps: BitRate) -> Self {
+        self.max_bitrate = Some(bps);
+        self
+    }
+
     /// Enables detailed console logging inside OpenH264.
+    ///
+    /// With the `log` feature, output is forwarded to the `log` crate instead. OpenH264's error, warning, info
+    /// and debug messages keep their level, more detailed ones are logged as `trace`. Nothing is logged unless
+    /// this is enabled, regardless of the `log` crate's max level.
     pub const fn debug(mut self, value: bool) -> Self {
         self.debug = if value { WELS_LOG_DETAIL } else { WELS_LOG_QUIET };
         self
@@ -728,8 +1180,16 @@ impl EncoderConfig {
     }
 
     /// Set the maximum slice length
+    ///
+    /// Same as setting [`SliceMode::SizeLimited`] via [`slice_mode()`](Self::slice_mode).
     pub const fn max_slice_len(mut self, max_slice_len: u32) -> Self {
-        self.max_slice_len = Some(max_slice_len);
+        self.slice_mode = SliceMode::SizeLimited(max_slice_len);
+        self
+    }
+
+    /// Sets how frames are split into slices, see [`SliceMode`].
+    pub const fn slice_mode(mut self, value: SliceMode) -> Self {
+        self.slice_mode = value;
         self
     }
 
@@ -757,6 +1217,20 @@ impl EncoderConfig {
         self
     }
 
+    /// Configures the deblocking filter (on with zero offsets by default).
+    pub const fn deblocking(mut self, value: Deblocking) -> Self {
+        self.deblocking = value;
+        self
+    }
+
+    /// Sets the entropy coding method ([`EntropyCoding::Cavlc`] by default).
+    ///
+    /// Encoding fails if this is [`EntropyCoding::Cabac`] and a [`Profile`] without CABAC support was selected.
+    pub const fn entropy_coding(mut self, value: EntropyCoding) -> Self {
+        self.entropy_coding = value;
+        self
+    }
+
     /// Set scene change detect (on by default)
     pub const fn scene_change_detect(mut self, value: bool) -> Self {
         self.scene_change_detect = value;
@@ -781,6 +1255,21 @@ impl EncoderConfig {
         self
     }
 
+    /// Set the number of long term reference frames (chosen by OpenH264 by default)
+    ///
+    /// Only has an effect with [`long_term_reference()`](Self::long_term_reference) enabled. OpenH264 supports
+    /// at most 2 for [`UsageType::CameraVideoRealTime`] and 4 otherwise, encoding fails with larger values.
+    pub const fn ltr_ref_num(mut self, value: u8) -> Self {
+        self.ltr_ref_num = Some(value);
+        self
+    }
+
+    /// Set the minimum distance in frames between two long term reference frames (30 by default)
+    pub const fn ltr_mark_period(mut self, frames: u32) -> Self {
+        self.ltr_mark_period = frames;
+        self
+    }
+
     /// Set the interval of intra frames (0 by default, disabling periodic intra frames)
     pub const fn intra_frame_period(mut self, value: IntraFramePeriod) -> Self {
         self.intra_frame_period = value;
@@ -819,6 +1308,139 @@ impl EncoderConfig {
         self.vui = Some(config);
         self
     }
+
+    /// Adds a spatial layer to be encoded.
+    ///
+    /// If no layers are added, a single layer with the resolution of the input frame and the configured
+    /// [`bitrate()`](Self::bitrate) is encoded. Once layers are added, the overall target bitrate
+    /// becomes the sum of all layer bitrates. Layers must be added in ascending order of resolution.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use openh264::encoder::{BitRate, EncoderConfig, SpatialLayer};
+    ///
+    /// let config = EncoderConfig::new()
+    ///     .spatial_layer(SpatialLayer::new(320, 180, BitRate::from_bps(200_000)))
+    ///     .spatial_layer(SpatialLayer::new(1280, 720, BitRate::from_bps(1_500_000)))
+    ///     .simulcast(true);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than [`MAX_SPATIAL_LAYERS`] layers are added.
+    pub const fn spatial_layer(mut self, layer: SpatialLayer) -> Self {
+        let mut i = 0;
+
+        while i < MAX_SPATIAL_LAYERS {
+            if self.spatial_layers[i].is_none() {
+                self.spatial_layers[i] = Some(layer);
+                return self;
+            }
+            i += 1;
+        }
+
+        panic!("too many spatial layers");
+    }
+
+    /// Encodes spatial layers as independent AVC streams instead of SVC (off by default).
+    ///
+    /// With simulcast each spatial layer can be decoded on its own by any H.264 decoder, see
+    /// [`EncodedBitStream::write_spatial_layer_vec()`].
+    pub const fn simulcast(mut self, value: bool) -> Self {
+        self.simulcast = value;
+        self
+    }
+
+    /// Sets the number of temporal layers (1 by default).
+    ///
+    /// With `n` temporal layers the encoder uses a hierarchical GOP of size `2^(n-1)`, and
+    /// NALs of the upper layers (see [`Layer::temporal_id()`]) can be dropped to reduce the frame rate
+    /// without re-encoding. The [`intra_frame_period()`](Self::intra_frame_period) is rounded up
+    /// to a multiple of the GOP size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num` is not within `1..=4`.
+    pub const fn temporal_layers(mut self, num: u8) -> Self {
+        assert!(
+            num >= 1 && num as u32 <= openh264_sys2::MAX_TEMPORAL_LAYER_NUM,
+            "temporal layers out of range (1..=4)"
+        );
+        self.temporal_layers = num;
+        self
+    }
+
+    /// Sets the interval in which OpenH264 logs its [statistics](Encoder::statistics) (5 seconds by default).
+    ///
+    /// The interval is measured in terms of the timestamps passed to [`Encoder::encode_at()`].
+    pub const fn statistics_log_interval(mut self, value: Duration) -> Self {
+        self.statistics_log_interval = Some(value);
+        self
+    }
+
+    /// Emits a prefix NAL in front of each base layer slice (off by default).
+    ///
+    /// Prefix NALs carry the SVC layer information of the base layer. They are ignored with [`simulcast()`](Self::simulcast).
+    pub const fn prefix_nal(mut self, value: bool) -> Self {
+        self.prefix_nal = value;
+        self
+    }
+
+    /// Computes the PSNR of each encoded layer against its input (off by default).
+    ///
+    /// The values are available via [`Layer::psnr()`]. This requires comparing each reconstructed picture to the source and
+    /// costs some encoding performance.
+    pub const fn psnr(mut self, value: bool) -> Self {
+        self.psnr = value;
+        self
+    }
+}
+
+impl EncoderConfig {
+    /// Bit rates of all encoded spatial layers, and whether their max bit rate is pinned to it.
+    fn layer_bitrates(&self) -> [Option<(BitRate, bool)>; MAX_SPATIAL_LAYERS] {
+        if self.spatial_layers[0].is_none() {
+            let mut rval = [None; MAX_SPATIAL_LAYERS];
+            rval[0] = Some((self.target_bitrate, self.max_bitrate.is_none()));
+            rval
+        } else {
+            self.spatial_layers
+                .map(|x| x.map(|layer| (layer.bitrate, layer.max_bitrate.is_none())))
+        }
+    }
+
+    /// Checks the bit rates of all encoded spatial layers against what OpenH264 accepts when setting them at runtime.
+    fn validate_layer_bitrates(&self) -> Result<(), Error> {
+        let default_layer = [Some(SpatialLayer {
+            max_bitrate: self.max_bitrate,
+            ..SpatialLayer::new(0, 0, self.target_bitrate)
+        })];
+        let layers = if self.spatial_layers[0].is_none() {
+            &default_layer[..]
+        } else {
+            &self.spatial_layers[..]
+        };
+
+        for layer in layers.iter().flatten() {
+            let bitrate = i32::try_from(layer.bitrate.0)?;
+            let frame_rate = layer.frame_rate.unwrap_or(self.max_frame_rate).0;
+
+            if bitrate == 0 {
+                return Err(Error::msg("Bit rate of every layer must be greater than zero"));
+            }
+
+            if (bitrate as f32) < frame_rate {
+                return Err(Error::msg("Bit rate of every layer must be at least its frame rate"));
+            }
+
+            if layer.max_bitrate.is_some_and(|max| max.0 < layer.bitrate.0) {
+                return Err(Error::msg("Bit rate of every layer must not exceed its max bit rate"));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// An [OpenH264](https://github.com/cisco/openh264) encoder.
@@ -827,6 +1449,7 @@ pub struct Encoder {
     raw_api: EncoderRawAPI,
     bit_stream_info: SFrameBSInfo,
     previous_dimensions: Option<(i32, i32)>,
+    conversion_buffer: Option<YUVBuffer>,
 }
 
 unsafe impl Send for Encoder {}
@@ -846,16 +1469,9 @@ impl Encoder {
     #[cfg(feature = "source")]
     pub fn new() -> Result<Self, Error> {
         let api = OpenH264API::from_source();
-        let config = EncoderConfig::new();
-        let raw_api = EncoderRawAPI::new(api)?;
-
-        Ok(Self {
-            config,
-            raw_api,
-            bit_stream_info: SFrameBSInfo::default(),
-            previous_dimensions: None,
-        })
+        Self::with_api_config(api, EncoderConfig::new())
     }
+
     /// Create an encoder with the provided [API](OpenH264API) and [configuration](EncoderConfig).
     ///
     /// The width and height will be taken from the [`YUVSource`] when calling [`Encoder::encode()`].
@@ -866,11 +1482,25 @@ impl Encoder {
     pub fn with_api_config(api: OpenH264API, config: EncoderConfig) -> Result<Self, Error> {
         let raw_api = EncoderRawAPI::new(api)?;
 
+        #[cfg(feature = "log")]
+        unsafe {
+            let mut callback: WelsTraceCallback = Some(crate::trace::encoder_callback);
+            let mut context = crate::trace::new_instance();
+
+            raw_api
+                .set_option(ENCODER_OPTION_TRACE_CALLBACK, addr_of_mut!(callback).cast())
+                .ok()?;
+            raw_api
+                .set_option(ENCODER_OPTION_TRACE_CALLBACK_CONTEXT, addr_of_mut!(context).cast())
+                .ok()?;
+        }
+
         Ok(Self {
             config,
             raw_api,
             bit_stream_info: SFrameBSInfo::default(),
             previous_dimensions: None,
+            conversion_buffer: None,
         })
     }
 
@@ -886,8 +1516,8 @@ impl Encoder {
     /// # Errors
     ///
     /// This might error for various reasons, many of which aren't clearly documented in OpenH264.
-    pub fn encode<T: YUVSource>(&mut self, yuv_source: &T) -> Result<EncodedBitStream<'_>, Error> {
-        self.encode_at(yuv_source, Timestamp::ZERO)
+    pub fn encode<T: EncoderInput>(&mut self, source: &T) -> Result<EncodedBitStream<'_>, Error> {
+        self.encode_at(source, Timestamp::ZERO)
     }
 
     /// Encodes a YUV source and returns the encoded bitstream.
@@ -899,6 +1529,9 @@ impl Encoder {
     /// The resolution of the encoded frame is allowed to change. Each time it changes, the
     /// encoder is re-initialized with the new values.
     ///
+    /// Frames of any size can be encoded. Since H.264 can only crop 4:2:0 frames to even sizes, odd widths or heights are
+    /// padded by repeating the last column or row, so decoded frames will be one pixel larger in that dimension.
+    ///
     /// # Panics
     ///
     /// Panics if the provided timestamp as milliseconds is out of range of i64.
@@ -906,9 +1539,25 @@ impl Encoder {
     /// # Errors
     ///
     /// This might error for various reasons, many of which aren't clearly documented in OpenH264.
-    pub fn encode_at<T: YUVSource>(&mut self, yuv_source: &T, timestamp: Timestamp) -> Result<EncodedBitStream<'_>, Error> {
+    pub fn encode_at<T: EncoderInput>(&mut self, source: &T, timestamp: Timestamp) -> Result<EncodedBitStream<'_>, Error> {
+        // Sources needing conversion are written into a buffer we keep around between frames.
+        let mut buffer = self.conversion_buffer.take();
+        let result = self.encode_yuv(source.as_yuv(&mut buffer), timestamp);
+        self.conversion_buffer = buffer;
+        result?;
+
+        Ok(EncodedBitStream {
+            bit_stream_info: &self.bit_stream_info,
+        })
+    }
+
+    fn encode_yuv(&mut self, yuv_source: &dyn YUVSource, timestamp: Timestamp) -> Result<(), Error> {
         let new_dimensions = yuv_source.dimensions_i32();
 
+        if new_dimensions.0 % 2 == 1 || new_dimensions.1 % 2 == 1 {
+            return Err(Error::msg("Encoder input must be padded to even dimensions"));
+        }
+
         if self.previous_dimensions != Some(new_dimensions) {
             self.reinit(new_dimensions.0, new_dimensions.1)?;
             self.previous_dimensions = Some(new_dimensions);
@@ -930,20 +1579,16 @@ impl Encoder {
             iPicWidth: new_dimensions.0,
             iPicHeight: new_dimensions.1,
             uiTimeStamp: timestamp.as_native(),
-            bPsnrY: false,
-            bPsnrU: false,
-            bPsnrV: false,
+            bPsnrY: self.config.psnr,
+            bPsnrU: self.config.psnr,
+            bPsnrV: self.config.psnr,
         };
 
         unsafe {
             self.raw_api
                 .encode_frame(&raw const source, &raw mut self.bit_stream_info)
-                .ok()?;
+                .ok()
         }
-
-        Ok(EncodedBitStream {
-            bit_stream_info: &self.bit_stream_info,
-        })
     }
 
     #[rustfmt::skip]
@@ -967,6 +1612,7 @@ impl Encoder {
 
         params.iPicWidth = width as c_int; // If we do .into() instead, could this fail to compile on some platforms?
         params.iPicHeight = height as c_int; // If we do .into() instead, could this fail to compile on some platforms?
+        params.bEnableFrameCroppingFlag = true; // Sizes not divisible by 16 are padded internally, and cropped by decoders
         params.iRCMode = self.config.rate_control_mode.to_c();
         params.bEnableFrameSkip = self.config.enable_skip_frame;
         params.iTargetBitrate = self.config.target_bitrate.0.try_into()?;
@@ -980,49 +1626,73 @@ impl Encoder {
         params.bEnableAdaptiveQuant = self.config.adaptive_quantization;
         params.bEnableBackgroundDetection = self.config.background_detection;
         params.bEnableLongTermReference = self.config.long_term_reference;
+
+        if let Some(ltr_ref_num) = self.config.ltr_ref_num {
+            // OpenH264 would silently replace a larger count with the one it supports.
+            let max_ltr_ref_num = if matches!(self.config.usage_type, UsageType::CameraVideoRealTime) { 2 } else { 4 };
+
+            if self.config.long_term_reference && ltr_ref_num > max_ltr_ref_num {
+                return Err(Error::msg("Number of long term reference frames exceeds what the usage type supports"));
+            }
+
+            params.iLTRRefNum = ltr_ref_num.into();
+        }
+
         params.iComplexityMode = self.config.complexity.to_c();
         params.uiIntraPeriod = self.config.intra_frame_period.0;
-        params.iLoopFilterDisableIdc = DEBLOCKING_IDC_0;
+        params.iLoopFilterDisableIdc = self.config.deblocking.mode.to_c();
+        params.iLoopFilterAlphaC0Offset = self.config.deblocking.alpha_offset.into();
+        params.iLoopFilterBetaOffset = self.config.deblocking.beta_offset.into();
+        params.iEntropyCodingModeFlag = self.config.entropy_coding.to_c();
         params.iMinQp = self.config.qp.min.into();
         params.iMaxQp = self.config.qp.max.into();
 
-        if let Some(profile) = self.config.profile {
-            params.sSpatialLayers[0].uiProfileIdc = profile.to_c();
-        }
+        params.iSpatialLayerNum = 1;
+        params.iTemporalLayerNum = self.config.temporal_layers.max(1).into();
+        params.bPrefixNalAddingCtrl = self.config.prefix_nal;
+        params.iLtrMarkPeriod = self.config.ltr_mark_period;
+        params.bSimulcastAVC = self.config.simulcast;
 
-        if let Some(level) = self.config.level {
-            params.sSpatialLayers[0].uiLevelIdc = level.to_c();
-        }
+        let mut default_layer = SpatialLayer::new(width as u32, height as u32, self.config.target_bitrate);
 
-        // Apply VUI (Video Usability Information) parameters for color space signaling
-        if let Some(ref vui) = self.config.vui {
-            params.sSpatialLayers[0].bVideoSignalTypePresent = true;
-            params.sSpatialLayers[0].bColorDescriptionPresent = true;
-            params.sSpatialLayers[0].bFullRange = vui.full_range;
-            params.sSpatialLayers[0].uiColorPrimaries = vui.color_primaries.as_u8();
-            params.sSpatialLayers[0].uiTransferCharacteristics = vui.transfer_characteristics.as_u8();
-            params.sSpatialLayers[0].uiColorMatrix = vui.matrix_coefficients.as_u8();
+        if let Some(max_bitrate) = self.config.max_bitrate {
+            params.iMaxBitrate = max_bitrate.0.try_into()?;
+            default_layer = default_layer.max_bitrate(max_bitrate);
         }
 
-        params.iSpatialLayerNum = 1;
-        params.iTemporalLayerNum = 1;
-        params.iLtrMarkPeriod = 30;
-        params.sSpatialLayers[0].iMaxSpatialBitrate = self.config.target_bitrate.0.try_into()?;
-        params.sSpatialLayers[0].iSpatialBitrate = self.config.target_bitrate.0.try_into()?;
-        params.sSpatialLayers[0].fFrameRate = self.config.max_frame_rate.0;
-        params.sSpatialLayers[0].iVideoWidth = width;
-        params.sSpatialLayers[0].iVideoHeight = height;
-
-        if let Some(max_slice_len) = self.config.max_slice_len {
-            // Limit the slice length by setting both MaxNalSize and uiSliceSizeConstraint
-            params.uiMaxNalSize = max_slice_len;
+        let mut layers = self.config.spatial_layers.iter().flatten().copied().peekable();
 
-            params.sSpatialLayers[0].sSliceArgument.uiSliceMode = SM_SIZELIMITED_SLICE;
-            params.sSpatialLayers[0].sSliceArgument.uiSliceSizeConstraint = max_slice_len;
+        if layers.peek().is_none() {
+            Self::apply_spatial_layer(&self.config, &mut params, 0, &default_layer)?;
         } else {
-            // No size limit, explicitly use defaults
-            params.sSpatialLayers[0].sSliceArgument.uiSliceMode = SM_SINGLE_SLICE;
-            params.sSpatialLayers[0].sSliceArgument.uiSliceNum = 1;
+            let mut total_bitrate = 0;
+            let mut previous = (0, 0);
+
+            for (i, layer) in layers.enumerate() {
+                if layer.width == 0 || layer.height == 0 || layer.width > width as u32 || layer.height > height as u32 {
+                    return Err(Error::msg_string(format!(
+                        "Spatial layer {i} ({}x{}) must be non-empty and fit into input {width}x{height}",
+                        layer.width, layer.height
+                    )));
+                }
+
+                if layer.width < previous.0 || layer.height < previous.1 {
+                    return Err(Error::msg("Spatial layers must be given in ascending order of resolution"));
+                }
+
+                Self::apply_spatial_layer(&self.config, &mut params, i, &layer)?;
+
+                previous = (layer.width, layer.height);
+                total_bitrate += layer.bitrate.0;
+                params.iSpatialLayerNum = (i + 1).try_into()?;
+            }
+
+            params.iTargetBitrate = total_bitrate.try_into()?;
+        }
+
+        if let SliceMode::SizeLimited(max_slice_len) = self.config.slice_mode {
+            // Limit the slice length by setting MaxNalSize, the per-layer constraint is set above.
+            params.uiMaxNalSize = max_slice_len;
         }
 
         unsafe {
@@ -1031,6 +1701,11 @@ impl Encoder {
                 self.raw_api.initialize_ext(&raw const params).ok()?;
                 self.raw_api.set_option(ENCODER_OPTION_TRACE_LEVEL, addr_of_mut!(self.config.debug).cast()).ok()?;
                 self.raw_api.set_option(ENCODER_OPTION_DATAFORMAT, addr_of_mut!(self.config.data_format).cast()).ok()?;
+
+                if let Some(interval) = self.config.statistics_log_interval {
+                    let mut interval_ms: c_int = interval.as_millis().try_into()?;
+                    self.raw_api.set_option(ENCODER_OPTION_STATISTICS_LOG_INTERVAL, addr_of_mut!(interval_ms).cast()).ok()?;
+                }
             } else {
                 // Subsequent times we call SetOption
                 self.raw_api.set_option(ENCODER_OPTION_SVC_ENCODE_PARAM_EXT, addr_of_mut!(params).cast()).ok()?;
@@ -1043,6 +1718,58 @@ impl Encoder {
         Ok(())
     }
 
+    /// Writes a single [`SpatialLayer`] into `params.sSpatialLayers[i]`.
+    fn apply_spatial_layer(
+        config: &EncoderConfig,
+        params: &mut SEncParamExt,
+        i: usize,
+        layer: &SpatialLayer,
+    ) -> Result<(), Error> {
+        let layer_params = &mut params.sSpatialLayers[i];
+
+        layer_params.iVideoWidth = layer.width.try_into()?;
+        layer_params.iVideoHeight = layer.height.try_into()?;
+        layer_params.iSpatialBitrate = layer.bitrate.0.try_into()?;
+        layer_params.iMaxSpatialBitrate = layer.max_bitrate.unwrap_or(layer.bitrate).0.try_into()?;
+        layer_params.fFrameRate = layer.frame_rate.unwrap_or(config.max_frame_rate).0;
+
+        if let Some(profile) = layer.profile.or(config.profile) {
+            if config.entropy_coding == EntropyCoding::Cabac && !profile.supports_cabac() {
+                return Err(Error::msg_string(format!(
+                    "Profile {profile:?} of spatial layer {i} does not support CABAC"
+                )));
+            }
+
+            layer_params.uiProfileIdc = profile.to_c();
+        }
+
+        if let Some(level) = layer.level.or(config.level) {
+            layer_params.uiLevelIdc = level.to_c();
+        }
+
+        // Apply VUI (Video Usability Information) parameters for color space signaling
+        if let Some(ref vui) = config.vui {
+            layer_params.bVideoSignalTypePresent = true;
+            layer_params.bColorDescriptionPresent = true;
+            layer_params.bFullRange = vui.full_range;
+            layer_params.uiColorPrimaries = vui.color_primaries.as_u8();
+            layer_params.uiTransferCharacteristics = vui.transfer_characteristics.as_u8();
+            layer_params.uiColorMatrix = vui.matrix_coefficients.as_u8();
+
+            if let Some(sample_aspect_ratio) = vui.sample_aspect_ratio {
+                let (idc, ext_width, ext_height) = sample_aspect_ratio.to_c();
+                layer_params.bAspectRatioPresent = true;
+                layer_params.eAspectRatio = idc;
+                layer_params.sAspectRatioExtWidth = ext_width;
+                layer_params.sAspectRatioExtHeight = ext_height;
+            }
+        }
+
+        layer_params.sSliceArgument = config.slice_mode.to_c(layer.width, layer.height)?;
+
+        Ok(())
+    }
+
     /// Forces the encoder to emit an intra frame (I-frame, "keyframe") for the next encoded frame.
     pub fn force_intra_frame(&mut self) {
         // SAFETY: This should be safe, simply as there is no indication why it shouldn't be. We are
@@ -1052,6 +1779,249 @@ impl Encoder {
         }
     }
 
+    /// Changes the target bit rate without re-initializing the encoder.
+    ///
+    /// Encoder state is kept and no new IDR frame is forced. If [spatial layers](EncoderConfig::spatial_layer)
+    /// are configured, their bit rates are scaled proportionally, or split evenly if they were all zero.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the bit rate is zero, or if the bit rate of any layer would be zero, lower than its frame rate or
+    /// higher than its explicit max bit rate. These are checked up front, in which case nothing was changed.
+    ///
+    /// If OpenH264 still rejects one of the values, the native encoder may already use some of the new bit rates
+    /// while the encoder keeps reporting the old configuration. The next resolution change re-initializes it from that.
+    pub fn set_bitrate(&mut self, bps: BitRate) -> Result<(), Error> {
+        if bps.0 == 0 {
+            return Err(Error::msg("Bit rate must be greater than zero"));
+        }
+
+        let mut config = self.config;
+        let layers = config.spatial_layers.iter().flatten().count() as u64;
+        let total_layer_bitrate = config
+            .spatial_layers
+            .iter()
+            .flatten()
+            .map(|x| u64::from(x.bitrate.0))
+            .sum::<u64>();
+
+        // Keep the ratio between layers, splitting evenly if they don't have any bit rate yet.
+        for layer in config.spatial_layers.iter_mut().flatten() {
+            let bitrate = (u64::from(bps.0) * u64::from(layer.bitrate.0))
+                .checked_div(total_layer_bitrate)
+                .unwrap_or_else(|| u64::from(bps.0) / layers);
+            layer.bitrate.0 = bitrate.try_into()?;
+        }
+
+        config.target_bitrate = bps;
+
+        // OpenH264 applies every value on its own, so everything it could reject is checked before the first change.
+        config.validate_layer_bitrates()?;
+
+        // Before the first frame was encoded the values are simply picked up by `reinit`.
+        if self.previous_dimensions.is_some() {
+            let old_layers = self.config.layer_bitrates();
+            let new_layers = config.layer_bitrates();
+
+            // Layers without explicit max bit rate have it pinned to their bit rate by `reinit`. OpenH264 requires
+            // max >= bit rate at all times, so raise the max before and lower it after changing the bit rate.
+            for (i, (old, new)) in old_layers.iter().zip(&new_layers).enumerate() {
+                if let (Some((old, true)), Some((new, true))) = (old, new) {
+                    self.set_bitrate_option(ENCODER_OPTION_MAX_BITRATE, i.try_into()?, (*old).max(*new))?;
+                }
+            }
+
+            // `SPATIAL_LAYER_ALL` would redistribute the bit rate in `f32`, so explicit layers are set one by one.
+            if config.spatial_layers[0].is_none() {
+                self.set_bitrate_option(ENCODER_OPTION_BITRATE, SPATIAL_LAYER_ALL, bps)?;
+            } else {
+                for (i, layer) in new_layers.iter().enumerate() {
+                    if let Some((new, _)) = layer {
+                        self.set_bitrate_option(ENCODER_OPTION_BITRATE, i.try_into()?, *new)?;
+                    }
+                }
+            }
+
+            for (i, layer) in new_layers.iter().enumerate() {
+                if let Some((new, true)) = layer {
+                    self.set_bitrate_option(ENCODER_OPTION_MAX_BITRATE, i.try_into()?, *new)?;
+                }
+            }
+        }
+
+        self.config = config;
+
+        Ok(())
+    }
+
+    /// Changes the maximum bit rate without re-initializing the encoder.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the bit rate is zero or OpenH264 rejects the new value, e.g., because it is lower than the target bit rate.
+    pub fn set_max_bitrate(&mut self, bps: BitRate) -> Result<(), Error> {
+        if bps.0 == 0 {
+            return Err(Error::msg("Bit rate must be greater than zero"));
+        }
+
+        if self.previous_dimensions.is_some() {
+            // Without explicit spatial layers the single layer inherits the max bit rate, see `reinit`.
+            if self.config.spatial_layers[0].is_none() {
+                self.set_bitrate_option(ENCODER_OPTION_MAX_BITRATE, SPATIAL_LAYER_0, bps)?;
+            }
+
+            self.set_bitrate_option(ENCODER_OPTION_MAX_BITRATE, SPATIAL_LAYER_ALL, bps)?;
+        }
+
+        self.config.max_bitrate = Some(bps);
+
+        Ok(())
+    }
+
+    /// Changes the maximum frame rate without re-initializing the encoder.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the frame rate is not positive or OpenH264 rejects the new value.
+    pub fn set_frame_rate(&mut self, value: FrameRate) -> Result<(), Error> {
+        let mut hz = value.0;
+
+        if hz.is_nan() || hz <= 0.0 {
+            return Err(Error::msg("Frame rate must be greater than zero"));
+        }
+
+        // Before the first frame was encoded the value is simply picked up by `reinit`.
+        if self.previous_dimensions.is_some() {
+            unsafe {
+                self.raw_api
+                    .set_option(ENCODER_OPTION_FRAME_RATE, addr_of_mut!(hz).cast())
+                    .ok()?;
+            }
+        }
+
+        self.config.max_frame_rate = value;
+
+        Ok(())
+    }
+
+    fn set_bitrate_option(&mut self, option: ENCODER_OPTION, layer: LAYER_NUM, bps: BitRate) -> Result<(), Error> {
+        let mut info = SBitrateInfo {
+            iLayer: layer,
+            iBitrate: bps.0.try_into()?,
+        };
+
+        unsafe { self.raw_api.set_option(option, addr_of_mut!(info).cast()).ok() }
+    }
+
+    /// Tells the encoder that the receiver lost frames, so it can repair the stream.
+    ///
+    /// With [long term references](EncoderConfig::long_term_reference) enabled the encoder recovers by
+    /// referencing a previously acknowledged LTR frame instead of sending a new IDR frame. Without LTR, or if
+    /// `last_correct_frame_num` is `None`, the next frame will be an IDR frame.
+    ///
+    /// - `spatial_id` - the spatial layer the loss was observed on, `0` without [spatial layers](EncoderConfig::spatial_layer).
+    /// - `idr_pic_id` - the `idr_pic_id` of the IDR frame the lost frames depend on, as reported by the decoder.
+    /// - `last_correct_frame_num` - the `frame_num` of the last correctly decoded frame, if any.
+    /// - `current_frame_num` - the `frame_num` of the current (broken) frame, if known.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no frame was encoded yet, or OpenH264 rejected the request.
+    pub fn ltr_recovery_request(
+        &mut self,
+        spatial_id: usize,
+        idr_pic_id: u32,
+        last_correct_frame_num: Option<u32>,
+        current_frame_num: Option<u32>,
+    ) -> Result<(), Error> {
+        if self.previous_dimensions.is_none() {
+            return Err(Error::msg("LTR recovery requests are only possible after encoding a frame"));
+        }
+
+        let mut request = SLTRRecoverRequest {
+            uiFeedbackType: LTR_RECOVERY_REQUEST as c_uint,
+            uiIDRPicId: idr_pic_id,
+            iLastCorrectFrameNum: last_correct_frame_num.map_or(Ok(-1), TryInto::try_into)?,
+            iCurrentFrameNum: current_frame_num.map_or(Ok(-1), TryInto::try_into)?,
+            iLayerId: spatial_id.try_into()?,
+        };
+
+        unsafe {
+            self.raw_api
+                .set_option(ENCODER_LTR_RECOVERY_REQUEST, addr_of_mut!(request).cast())
+                .ok()?;
+        }
+
+        Ok(())
+    }
+
+    /// Tells the encoder whether the receiver successfully decoded a long term reference frame.
+    ///
+    /// Once marking succeeded, the encoder may use that frame to recover from losses, see
+    /// [`Encoder::ltr_recovery_request()`].
+    ///
+    /// - `spatial_id` - the spatial layer the frame was received on, `0` without [spatial layers](EncoderConfig::spatial_layer).
+    /// - `idr_pic_id` - the `idr_pic_id` of the IDR frame the LTR frame belongs to, as reported by the decoder.
+    /// - `ltr_frame_num` - the `frame_num` of the LTR frame, as reported by the decoder.
+    ///
+    /// # Errors
+    ///
+    /// Fails if [long term references](EncoderConfig::long_term_reference) are disabled, no frame was encoded yet,
+    /// or OpenH264 rejected the feedback.
+    pub fn ltr_marking_feedback(
+        &mut self,
+        spatial_id: usize,
+        idr_pic_id: u32,
+        ltr_frame_num: u32,
+        marking: LtrMarking,
+    ) -> Result<(), Error> {
+        if !self.config.long_term_reference {
+            return Err(Error::msg("LTR marking feedback requires long term references to be enabled"));
+        }
+
+        if self.previous_dimensions.is_none() {
+            return Err(Error::msg("LTR marking feedback is only possible after encoding a frame"));
+        }
+
+        let mut feedback = SLTRMarkingFeedback {
+            uiFeedbackType: marking.to_c(),
+            uiIDRPicId: idr_pic_id,
+            iLTRFrameNum: ltr_frame_num.try_into()?,
+            iLayerId: spatial_id.try_into()?,
+        };
+
+        unsafe {
+            self.raw_api
+                .set_option(ENCODER_LTR_MARKING_FEEDBACK, addr_of_mut!(feedback).cast())
+                .ok()?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns statistics about the encoding process so far.
+    ///
+    /// With multiple [spatial layers](EncoderConfig::spatial_layer) these are the statistics of the highest layer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no frame was encoded yet.
+    pub fn statistics(&mut self) -> Result<EncoderStatistics, Error> {
+        if self.previous_dimensions.is_none() {
+            return Err(Error::msg("Statistics are only available after encoding a frame"));
+        }
+
+        let mut stats = SEncoderStatistics::default();
+
+        unsafe {
+            self.raw_api
+                .get_option(ENCODER_OPTION_GET_STATISTICS, addr_of_mut!(stats).cast())
+                .ok()?;
+        }
+
+        Ok(EncoderStatistics::from_native(&stats))
+    }
+
     /// Obtain the raw API for advanced use cases.
     ///
     /// When resorting to this call, please consider filing an issue / PR.
@@ -1124,6 +2094,27 @@ impl<'a> EncodedBitStream<'a> {
         }
     }
 
+    /// Writes all NAL units belonging to the given spatial layer into the given Vec.
+    ///
+    /// When encoding with [`EncoderConfig::simulcast()`], each spatial layer carries its own SPS / PPS,
+    /// and the output is a complete H.264 stream of that layer's resolution.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn write_spatial_layer_vec(&self, spatial_id: usize, dst: &mut Vec<u8>) {
+        for l in 0..self.num_layers() {
+            let layer = self.layer(l).unwrap();
+
+            if layer.spatial_id() != spatial_id {
+                continue;
+            }
+
+            for n in 0..layer.nal_count() {
+                let nal = layer.nal_unit(n).unwrap();
+
+                dst.extend_from_slice(nal);
+            }
+        }
+    }
+
     /// Writes the current bitstream into the given Writer.
     ///
     /// # Errors
@@ -1158,6 +2149,128 @@ impl<'a> EncodedBitStream<'a> {
         self.write_vec(&mut rval);
         rval
     }
+
+    /// Copies the bitstream into an [`EncodedFrame`], keeping frame type, timestamp and NAL unit boundaries.
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn to_frame(&self) -> EncodedFrame {
+        let mut data = Vec::with_capacity(self.bit_stream_info.iFrameSizeInBytes as usize);
+        let mut nal_units = Vec::new();
+
+        for l in 0..self.num_layers() {
+            let layer = self.layer(l).unwrap();
+
+            for n in 0..layer.nal_count() {
+                let nal = layer.nal_unit(n).unwrap();
+
+                nal_units.push(NalUnitInfo {
+                    offset: data.len(),
+                    len: nal.len(),
+                    nal_type: NalUnitType::from_nal(nal),
+                    spatial_id: layer.spatial_id(),
+                    temporal_id: layer.temporal_id(),
+                });
+                data.extend_from_slice(nal);
+            }
+        }
+
+        EncodedFrame {
+            data,
+            nal_units,
+            frame_type: self.frame_type(),
+            timestamp: Timestamp::from_native(self.bit_stream_info.uiTimeStamp),
+        }
+    }
+}
+
+/// Encoded frame owning its bitstream, as returned by [`EncodedBitStream::to_frame()`].
+///
+/// Unlike [`EncodedBitStream`] it does not borrow the [`Encoder`], so it can be kept across calls to
+/// [`Encoder::encode()`], or sent to a muxer or network writer on another thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedFrame {
+    data: Vec<u8>,
+    nal_units: Vec<NalUnitInfo>,
+    frame_type: FrameType,
+    timestamp: Timestamp,
+}
+
+impl EncodedFrame {
+    /// The Annex B bitstream of this frame, i.e., all NAL units with start codes.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the bitstream, dropping the metadata.
+    #[must_use]
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Total size of the bitstream in bytes.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// True if the encoder produced no output, e.g., for skipped frames.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Frame type of the encoded packet.
+    #[must_use]
+    pub const fn frame_type(&self) -> FrameType {
+        self.frame_type
+    }
+
+    /// True if this is an IDR frame, from which a decoder can start decoding.
+    #[must_use]
+    pub fn is_idr(&self) -> bool {
+        self.frame_type == FrameType::IDR
+    }
+
+    /// Timestamp passed to [`Encoder::encode_at()`] for this frame.
+    #[must_use]
+    pub const fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Position, type and layer of every NAL unit in [`data()`](Self::data), in bitstream order.
+    #[must_use]
+    pub fn nal_units(&self) -> &[NalUnitInfo] {
+        &self.nal_units
+    }
+
+    /// Returns the ith NAL unit, including its start code.
+    #[must_use]
+    pub fn nal_unit(&self, i: usize) -> Option<&[u8]> {
+        let info = self.nal_units.get(i)?;
+        Some(&self.data[info.offset..info.offset + info.len])
+    }
+}
+
+impl AsRef<[u8]> for EncodedFrame {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Position and type of a NAL unit within an [`EncodedFrame`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NalUnitInfo {
+    /// Offset of the NAL unit's start code within [`EncodedFrame::data()`].
+    pub offset: usize,
+    /// Length of the NAL unit in bytes, including its start code.
+    pub len: usize,
+    /// Type from the NAL unit header.
+    pub nal_type: NalUnitType,
+    /// Spatial layer the NAL unit belongs to, see [`Layer::spatial_id()`].
+    pub spatial_id: usize,
+    /// Temporal layer the NAL unit belongs to, see [`Layer::temporal_id()`].
+    pub temporal_id: usize,
 }
 
 /// An encoded layer, contains the Network Abstraction Layer inputs.
@@ -1205,11 +2318,132 @@ impl<'a> Layer<'a> {
         }
     }
 
+    /// Spatial layer this layer belongs to, `0` being the lowest resolution.
+    #[must_use]
+    pub const fn spatial_id(&self) -> usize {
+        self.layer_info.uiSpatialId as usize
+    }
+
+    /// Temporal layer this layer belongs to, `0` being the base layer.
+    #[must_use]
+    pub const fn temporal_id(&self) -> usize {
+        self.layer_info.uiTemporalId as usize
+    }
+
+    /// Quality layer this layer belongs to.
+    #[must_use]
+    pub const fn quality_id(&self) -> usize {
+        self.layer_info.uiQualityId as usize
+    }
+
+    /// Sub-sequence ID of this layer.
+    #[must_use]
+    pub const fn sub_seq_id(&self) -> i32 {
+        self.layer_info.iSubSeqId
+    }
+
+    /// Type of this layer.
+    #[must_use]
+    pub const fn layer_type(&self) -> LayerType {
+        if self.is_video() {
+            LayerType::Video
+        } else {
+            LayerType::NonVideo
+        }
+    }
+
     /// If this is a video layer or not.
     #[must_use]
     pub const fn is_video(&self) -> bool {
         self.layer_info.uiLayerType == VIDEO_CODING_LAYER as c_uchar
     }
+
+    /// PSNR of this layer against its input, if enabled via [`EncoderConfig::psnr()`].
+    ///
+    /// Returns `None` for non-video layers, or if PSNR calculation is disabled.
+    #[must_use]
+    pub fn psnr(&self) -> Option<Psnr> {
+        let [y, u, v] = self.layer_info.rPsnr;
+
+        if self.is_video() && (y != 0.0 || u != 0.0 || v != 0.0) {
+            Some(Psnr { y, u, v })
+        } else {
+            None
+        }
+    }
+}
+
+/// Peak signal-to-noise ratio of an encoded [`Layer`], in dB.
+///
+/// Identical planes are reported as `99.99`.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Default)]
+pub struct Psnr {
+    /// PSNR of the luma plane.
+    pub y: f32,
+    /// PSNR of the U chroma plane.
+    pub u: f32,
+    /// PSNR of the V chroma plane.
+    pub v: f32,
+}
+
+/// Type of an encoded [`Layer`].
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
+pub enum LayerType {
+    /// Parameter sets (SPS, PPS) and other non-VCL NAL units.
+    NonVideo,
+    /// Video coding layer, containing slice data.
+    Video,
+}
+
+/// Statistics of an [`Encoder`], as returned by [`Encoder::statistics()`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EncoderStatistics {
+    /// Width of the encoded frames.
+    pub width: u32,
+    /// Height of the encoded frames.
+    pub height: u32,
+    /// Average time needed to encode a frame, in milliseconds.
+    pub average_encoding_time_ms: f32,
+    /// Average input frame rate since encoding started, based on the timestamps passed in.
+    pub average_frame_rate: f32,
+    /// Input frame rate within the last second, based on the timestamps passed in.
+    pub latest_frame_rate: f32,
+    /// Output bit rate in bits per second, measured over the statistics interval.
+    pub bitrate: u32,
+    /// Average QP of the last encoded frame.
+    pub average_frame_qp: u32,
+    /// Number of frames passed to the encoder.
+    pub input_frame_count: u32,
+    /// Number of frames skipped by rate control.
+    pub skipped_frame_count: u32,
+    /// Number of times the resolution changed.
+    pub resolution_change_count: u32,
+    /// Number of IDR frames requested.
+    pub idr_request_count: u32,
+    /// Number of IDR frames actually sent.
+    pub idr_sent_count: u32,
+    /// Number of long term reference frames sent.
+    pub ltr_sent_count: u32,
+}
+
+impl EncoderStatistics {
+    const fn from_native(stats: &SEncoderStatistics) -> Self {
+        Self {
+            width: stats.uiWidth,
+            height: stats.uiHeight,
+            average_encoding_time_ms: stats.fAverageFrameSpeedInMs,
+            average_frame_rate: stats.fAverageFrameRate,
+            latest_frame_rate: stats.fLatestFrameRate,
+            bitrate: stats.uiBitRate,
+            average_frame_qp: stats.uiAverageFrameQP,
+            input_frame_count: stats.uiInputFrameCount,
+            skipped_frame_count: stats.uiSkippedFrameCount,
+            resolution_change_count: stats.uiResolutionChangeTimes,
+            idr_request_count: stats.uiIDRReqNum,
+            idr_sent_count: stats.uiIDRSentNum,
+            ltr_sent_count: stats.uiLTRSentNum,
+        }
+    }
 }
 
 /// Frame type returned by the encoder.
@@ -1231,6 +2465,63 @@ pub enum FrameType {
     IPMixed,
 }
 
+/// Type of a NAL unit, as given by the `nal_unit_type` field of its header.
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum NalUnitType {
+    /// Coded slice of a non-IDR picture.
+    Slice,
+    /// Coded slice of an IDR picture.
+    SliceIdr,
+    /// Supplemental enhancement information.
+    Sei,
+    /// Sequence parameter set.
+    Sps,
+    /// Picture parameter set.
+    Pps,
+    /// Prefix NAL unit, preceding base layer slices in SVC streams.
+    Prefix,
+    /// Subset sequence parameter set of SVC streams.
+    SubsetSps,
+    /// Coded slice extension of SVC streams.
+    SliceExtension,
+    /// Any other type, with its `nal_unit_type` value.
+    Other(u8),
+}
+
+impl NalUnitType {
+    /// Type of the given NAL unit, which may start with a start code.
+    const fn from_nal(nal: &[u8]) -> Self {
+        let mut i = 0;
+        while i < nal.len() && nal[i] == 0 {
+            i += 1;
+        }
+        // Skip the `1` ending a start code.
+        if i >= 2 && i < nal.len() && nal[i] == 1 {
+            i += 1;
+        }
+
+        if i < nal.len() {
+            Self::from_u8(nal[i] & 0x1F)
+        } else {
+            Self::Other(0)
+        }
+    }
+
+    const fn from_u8(nal_unit_type: u8) -> Self {
+        match nal_unit_type {
+            1 => Self::Slice,
+            5 => Self::SliceIdr,
+            6 => Self::Sei,
+            7 => Self::Sps,
+            8 => Self::Pps,
+            14 => Self::Prefix,
+            15 => Self::SubsetSps,
+            20 => Self::SliceExtension,
+            other => Self::Other(other),
+        }
+    }
+}
+
 impl FrameType {
     const fn from_c_int(native: std::os::raw::c_int) -> Self {
         use openh264_sys2::{videoFrameTypeI, videoFrameTypeIDR, videoFrameTypeIPMixed, videoFrameTypeP, videoFrameTypeSkip};
@@ -0,0 +1,91 @@
+/// Matrix coefficients used to convert between RGB and YUV.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorMatrix {
+    /// ITU-R BT.601 (Kr=0.299, Kb=0.114), used for SD content.
+    #[default]
+    Bt601,
+    /// ITU-R BT.709 (Kr=0.2126, Kb=0.0722), used for HD content.
+    Bt709,
+    /// ITU-R BT.2020 non-constant luminance (Kr=0.2627, Kb=0.0593), used for UHD content.
+    Bt2020,
+}
+
+impl ColorMatrix {
+    /// Luma weights `(Kr, Kb)` of the red and blue channels; green is `1 - Kr - Kb`.
+    #[must_use]
+    pub const fn kr_kb(self) -> (f32, f32) {
+        match self {
+            Self::Bt601 => (0.299, 0.114),
+            Self::Bt709 => (0.2126, 0.0722),
+            Self::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+}
+
+/// Matrix and value range used when converting between RGB and YUV.
+///
+/// The default is BT.601 with limited range (Y in 16-235, U and V in 16-240), which is what
+/// [`YUVBuffer::read_rgb8()`](crate::formats::YUVBuffer::read_rgb8) and
+/// [`DecodedYUV::write_rgb8()`](crate::decoder::DecodedYUV::write_rgb8) use. If you signal a different
+/// color space in the bitstream, e.g., via [`VuiConfig`](crate::encoder::VuiConfig), convert with the
+/// matching one so colors don't shift.
+///
+/// # Example
+///
+/// ```rust
+/// use openh264::formats::{ColorMatrix, ColorSpace};
+///
+/// let color_space = ColorSpace::bt709().full_range(true);
+///
+/// assert_eq!(color_space.color_matrix(), ColorMatrix::Bt709);
+/// assert!(color_space.is_full_range());
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[must_use]
+pub struct ColorSpace {
+    color_matrix: ColorMatrix,
+    full_range: bool,
+}
+
+impl ColorSpace {
+    /// Creates a new color space from the given matrix and range.
+    pub const fn new(color_matrix: ColorMatrix, full_range: bool) -> Self {
+        Self {
+            color_matrix,
+            full_range,
+        }
+    }
+
+    /// BT.601 with limited range (default).
+    pub const fn bt601() -> Self {
+        Self::new(ColorMatrix::Bt601, false)
+    }
+
+    /// BT.709 with limited range.
+    pub const fn bt709() -> Self {
+        Self::new(ColorMatrix::Bt709, false)
+    }
+
+    /// BT.2020 with limited range.
+    pub const fn bt2020() -> Self {
+        Self::new(ColorMatrix::Bt2020, false)
+    }
+
+    /// Set full range (0-255) instead of limited range.
+    pub const fn full_range(mut self, value: bool) -> Self {
+        self.full_range = value;
+        self
+    }
+
+    /// The matrix coefficients.
+    #[must_use]
+    pub const fn color_matrix(self) -> ColorMatrix {
+        self.color_matrix
+    }
+
+    /// True for full range (0-255), false for limited range.
+    #[must_use]
+    pub const fn is_full_range(self) -> bool {
+        self.full_range
+    }
+}
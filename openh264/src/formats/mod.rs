@@ -19,13 +19,19 @@
 //! ```
 //!
 
+mod color;
+mod nv12;
+mod packed;
 mod rgb;
 pub(crate) mod rgb2yuv;
 mod yuv;
 pub(crate) mod yuv2rgb;
 
+pub use color::{ColorMatrix, ColorSpace};
+pub use nv12::NV12Slices;
+pub use packed::{UyvySliceU8, YuyvSliceU8, YvyuSliceU8};
 pub use rgb::{
-    AbgrSliceU8, AbgrSliceU32, ArgbSliceU8, ArgbSliceU32, BgrSliceU8, BgraSliceU8, BgraSliceU32, RGB8Source, RGBSource,
-    RgbSliceU8, RgbaSliceU8, RgbaSliceU32,
+    AbgrSliceU8, AbgrSliceU32, ArgbSliceU8, ArgbSliceU32, BgrSliceU8, BgraSliceU8, BgraSliceU32, RGB8Layout, RGB8Source,
+    RGBSource, RgbSliceU8, RgbaSliceU8, RgbaSliceU32,
 };
-pub use yuv::{YUVBuffer, YUVSlices, YUVSource};
+pub use yuv::{EncoderInput, YUVBuffer, YUVSlices, YUVSource};
@@ -0,0 +1,217 @@
+use wide::bytemuck::cast;
+use wide::{i16x8, u8x16};
+
+/// Order of the chroma samples in an interleaved UV plane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ChromaOrder {
+    Uv,
+    Vu,
+}
+
+/// Wrapper for semi-planar 4:2:0 data, as commonly produced by cameras and hardware decoders.
+///
+/// NV12 consists of a full size Y plane followed by a plane of interleaved `[U V U V ...]` samples at half the
+/// resolution in both directions. NV21 is the same, but with `[V U V U ...]` samples.
+///
+/// This can be passed to the [Encoder](crate::encoder::Encoder) directly, which splits the chroma plane before encoding.
+/// Use [`YUVBuffer::read_nv12()`](crate::formats::YUVBuffer::read_nv12) to convert it yourself.
+#[must_use]
+#[derive(Clone, Copy, Debug)]
+pub struct NV12Slices<'a> {
+    dimensions: (usize, usize),
+    y: &'a [u8],
+    uv: &'a [u8],
+    strides: (usize, usize),
+    order: ChromaOrder,
+}
+
+impl<'a> NV12Slices<'a> {
+    /// Creates a new NV12 slice with interleaved `[U V U V ...]` samples.
+    ///
+    /// Assume you have some dimension `(w, h)` that is your actual image size. In addition,
+    /// you will have strides `(sy, suv)` that specify how many bytes per row are actually used.
+    /// Strides must be larger or equal than `w` (y) or `2 * w.div_ceil(2)` (uv) respectively.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the given slices, strides or dimensions don't match.
+    pub fn new(y: &'a [u8], uv: &'a [u8], dimensions: (usize, usize), strides: (usize, usize)) -> Self {
+        assert!(strides.0 >= dimensions.0);
+        assert!(strides.1 >= 2 * dimensions.0.div_ceil(2));
+
+        assert_eq!(dimensions.1 * strides.0, y.len());
+        assert_eq!(dimensions.1.div_ceil(2) * strides.1, uv.len());
+
+        Self {
+            dimensions,
+            y,
+            uv,
+            strides,
+            order: ChromaOrder::Uv,
+        }
+    }
+
+    /// Creates a new NV21 slice with interleaved `[V U V U ...]` samples.
+    ///
+    /// See [`NV12Slices::new()`] for the meaning of the parameters.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the given slices, strides or dimensions don't match.
+    pub fn new_nv21(y: &'a [u8], vu: &'a [u8], dimensions: (usize, usize), strides: (usize, usize)) -> Self {
+        Self {
+            order: ChromaOrder::Vu,
+            ..Self::new(y, vu, dimensions, strides)
+        }
+    }
+
+    /// Size of the image as `(w, h)`.
+    #[must_use]
+    pub const fn dimensions(&self) -> (usize, usize) {
+        self.dimensions
+    }
+
+    /// Strides as `(y, uv)`.
+    #[must_use]
+    pub const fn strides(&self) -> (usize, usize) {
+        self.strides
+    }
+
+    /// Y plane.
+    #[must_use]
+    pub const fn y(&self) -> &[u8] {
+        self.y
+    }
+
+    /// Interleaved chroma plane, `[U V ...]` for NV12 and `[V U ...]` for NV21.
+    #[must_use]
+    pub const fn uv(&self) -> &[u8] {
+        self.uv
+    }
+}
+
+/// Splits NV12 / NV21 data into the given I420 planes, whose chroma planes must match the source dimensions.
+///
+/// Rows of `y_buf` are `y_stride` apart, which can be larger than the source width.
+#[allow(clippy::similar_names)]
+pub fn write_yuv_from_nv12(nv12: &NV12Slices, y_buf: &mut [u8], y_stride: usize, u_buf: &mut [u8], v_buf: &mut [u8]) {
+    let (width, height) = nv12.dimensions;
+    let chroma_width = width.div_ceil(2);
+
+    for (target, source) in y_buf
+        .chunks_exact_mut(y_stride)
+        .zip(nv12.y.chunks(nv12.strides.0))
+        .take(height)
+    {
+        target[..width].copy_from_slice(&source[..width]);
+    }
+
+    let (u_buf, v_buf) = match nv12.order {
+        ChromaOrder::Uv => (u_buf, v_buf),
+        ChromaOrder::Vu => (v_buf, u_buf),
+    };
+
+    let u_rows = u_buf.chunks_exact_mut(chroma_width);
+    let v_rows = v_buf.chunks_exact_mut(chroma_width);
+
+    for ((u_row, v_row), source) in u_rows.zip(v_rows).zip(nv12.uv.chunks(nv12.strides.1)) {
+        let simd_width = chroma_width - chroma_width % 16;
+        let (simd_u_row, u_row) = u_row.split_at_mut(simd_width);
+        let (simd_v_row, v_row) = v_row.split_at_mut(simd_width);
+        let (simd_source, source) = source.split_at(simd_width * 2);
+
+        for ((u, v), uv) in simd_u_row
+            .chunks_exact_mut(16)
+            .zip(simd_v_row.chunks_exact_mut(16))
+            .zip(simd_source.chunks_exact(32))
+        {
+            let (first, second) = deinterleave_simd(uv);
+            u.copy_from_slice(&first);
+            v.copy_from_slice(&second);
+        }
+
+        for ((u, v), uv) in u_row.iter_mut().zip(v_row.iter_mut()).zip(source.chunks_exact(2)) {
+            *u = uv[0];
+            *v = uv[1];
+        }
+    }
+}
+
+/// Splits 16 interleaved sample pairs into the 16 first and the 16 second samples.
+#[allow(clippy::inline_always)]
+#[inline(always)]
+fn deinterleave_simd(pairs: &[u8]) -> ([u8; 16], [u8; 16]) {
+    let low_byte = i16x8::splat(0xff);
+    // The first sample of each pair is the low byte of a 16 bit lane on little endian targets.
+    let (first_shift, second_shift) = if cfg!(target_endian = "little") { (0, 8) } else { (8, 0) };
+    let lanes = |pairs: &[u8]| cast::<[u8; 16], i16x8>(pairs.try_into().expect("16 bytes"));
+    let (a, b) = (lanes(&pairs[..16]), lanes(&pairs[16..]));
+
+    let first = u8x16::narrow_i16x8((a >> first_shift) & low_byte, (b >> first_shift) & low_byte);
+    let second = u8x16::narrow_i16x8((a >> second_shift) & low_byte, (b >> second_shift) & low_byte);
+
+    (first.to_array(), second.to_array())
+}
+
+#[cfg(test)]
+mod test {
+    use super::NV12Slices;
+    use crate::formats::{YUVBuffer, YUVSource};
+
+    #[test]
+    fn nv12_to_yuv_with_strides() {
+        let y = [1u8, 2, 3, 4, 0, 0, 5, 6, 7, 8, 0, 0];
+        let uv = [10u8, 20, 11, 21, 0, 0];
+        let yuv = YUVBuffer::from_nv12_source(&NV12Slices::new(&y, &uv, (4, 2), (6, 6)));
+
+        assert_eq!(yuv.y(), [1u8, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(yuv.u(), [10u8, 11]);
+        assert_eq!(yuv.v(), [20u8, 21]);
+    }
+
+    #[test]
+    fn nv21_to_yuv_odd() {
+        let y = [1u8, 2, 3, 4, 5, 6, 7, 8, 9];
+        let vu = [20u8, 10, 21, 11, 22, 12, 23, 13];
+        let yuv = YUVBuffer::from_nv12_source(&NV12Slices::new_nv21(&y, &vu, (3, 3), (3, 4)));
+
+        assert_eq!(yuv.y(), y);
+        assert_eq!(yuv.u(), [10u8, 11, 12, 13]);
+        assert_eq!(yuv.v(), [20u8, 21, 22, 23]);
+    }
+
+    #[test]
+    fn nv12_to_yuv_simd_matches_layout() {
+        for (w, h) in [(32_usize, 2_usize), (67, 3)] {
+            let (chroma_width, chroma_height) = (w.div_ceil(2), h.div_ceil(2));
+            let uv_stride = chroma_width * 2 + 6;
+            let y = (0..w * h).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+            let uv = (0..uv_stride * chroma_height)
+                .map(|i| (i * 89 % 241) as u8)
+                .collect::<Vec<_>>();
+            let plane = |offset: usize| {
+                (0..chroma_height)
+                    .flat_map(|row| (0..chroma_width).map(move |x| (x, row)))
+                    .map(|(x, row)| uv[row * uv_stride + x * 2 + offset])
+                    .collect::<Vec<_>>()
+            };
+
+            let yuv = YUVBuffer::from_nv12_source(&NV12Slices::new(&y, &uv, (w, h), (w, uv_stride)));
+            assert_eq!(yuv.y(), y);
+            assert_eq!(yuv.u(), plane(0));
+            assert_eq!(yuv.v(), plane(1));
+
+            let yuv = YUVBuffer::from_nv12_source(&NV12Slices::new_nv21(&y, &uv, (w, h), (w, uv_stride)));
+            assert_eq!(yuv.u(), plane(1));
+            assert_eq!(yuv.v(), plane(0));
+        }
+    }
+
+    #[test]
+    #[should_panic = "strides.1 >= 2 * dimensions.0.div_ceil(2)"]
+    fn nv12_uv_stride_too_small() {
+        let y = [0u8; 9];
+        let uv = [0u8; 6];
+        let _ = NV12Slices::new(&y, &uv, (3, 3), (3, 3));
+    }
+}
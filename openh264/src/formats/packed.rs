@@ -0,0 +1,319 @@
+use crate::formats::{EncoderInput, YUVBuffer, YUVSource};
+use wide::bytemuck::cast;
+use wide::{i16x8, i32x8, u8x16};
+
+/// Container for a slice of packed 4:2:2 `[Y0 U Y1 V Y0 U Y1 V ...]` data, also known as YUY2.
+///
+/// This is what most USB webcams deliver. It can be passed to the [Encoder](crate::encoder::Encoder) directly,
+/// which converts it to 4:2:0 before encoding.
+#[derive(Copy, Clone, Debug)]
+#[must_use]
+pub struct YuyvSliceU8<'a> {
+    data: &'a [u8],
+    dimensions: (usize, usize),
+    stride: usize,
+}
+
+/// Container for a slice of packed 4:2:2 `[U Y0 V Y1 U Y0 V Y1 ...]` data.
+#[derive(Copy, Clone, Debug)]
+#[must_use]
+pub struct UyvySliceU8<'a> {
+    data: &'a [u8],
+    dimensions: (usize, usize),
+    stride: usize,
+}
+
+/// Container for a slice of packed 4:2:2 `[Y0 V Y1 U Y0 V Y1 U ...]` data.
+#[derive(Copy, Clone, Debug)]
+#[must_use]
+pub struct YvyuSliceU8<'a> {
+    data: &'a [u8],
+    dimensions: (usize, usize),
+    stride: usize,
+}
+
+macro_rules! impl_packed_yuv422 {
+    ($t:ty, $offsets:expr) => {
+        impl<'a> $t {
+            /// Creates a new instance given the byte slice and dimensions.
+            ///
+            /// Rows hold `w.div_ceil(2)` macropixels of 4 bytes, for odd widths the last `Y1` is ignored.
+            ///
+            /// # Panics
+            ///
+            /// May panic if the slice length mismatches the given dimensions.
+            pub fn new(data: &'a [u8], dimensions: (usize, usize)) -> Self {
+                assert_eq!(data.len(), row_len(dimensions.0) * dimensions.1);
+
+                Self::with_stride(data, dimensions, row_len(dimensions.0))
+            }
+
+            /// Creates a new instance given the byte slice, dimensions and the distance between rows in bytes.
+            ///
+            /// The last row does not need to be padded to the full stride.
+            ///
+            /// # Panics
+            ///
+            /// May panic if the stride is smaller than a row, or if the slice is too short for the given dimensions.
+            pub fn with_stride(data: &'a [u8], dimensions: (usize, usize), stride: usize) -> Self {
+                assert!(stride >= row_len(dimensions.0));
+                assert!(data.len() >= min_len(dimensions, stride));
+
+                Self {
+                    data,
+                    dimensions,
+                    stride,
+                }
+            }
+
+            /// Size of the image as `(w, h)`.
+            #[must_use]
+            pub const fn dimensions(&self) -> (usize, usize) {
+                self.dimensions
+            }
+        }
+
+        impl<'a> EncoderInput for $t {
+            fn as_yuv<'b>(&'b self, buffer: &'b mut Option<YUVBuffer>) -> &'b dyn YUVSource {
+                YUVBuffer::write_even(buffer, self.dimensions, |y_buf, y_stride, u_buf, v_buf| {
+                    write_yuv_from_packed(
+                        self.data,
+                        self.dimensions,
+                        self.stride,
+                        $offsets,
+                        (y_buf, y_stride),
+                        u_buf,
+                        v_buf,
+                    );
+                })
+            }
+        }
+    };
+}
+
+// Byte offsets of Y0, U, Y1 and V within each 4 byte macropixel.
+impl_packed_yuv422!(YuyvSliceU8<'a>, [0, 1, 2, 3]);
+impl_packed_yuv422!(UyvySliceU8<'a>, [1, 0, 3, 2]);
+impl_packed_yuv422!(YvyuSliceU8<'a>, [0, 3, 2, 1]);
+
+/// Bytes in a row of `width` pixels, rounded up to whole macropixels.
+const fn row_len(width: usize) -> usize {
+    width.div_ceil(2) * 4
+}
+
+/// Minimum number of bytes needed for an image with the given row stride, where the last row need not be padded.
+const fn min_len(dimensions: (usize, usize), stride: usize) -> usize {
+    match dimensions.1 {
+        0 => 0,
+        h => (h - 1) * stride + row_len(dimensions.0),
+    }
+}
+
+/// Converts packed 4:2:2 data into 4:2:0 Y, U and V planes, averaging the chroma of each pair of rows.
+///
+/// The Y plane is given with its row stride, which can be larger than the width.
+#[allow(clippy::similar_names)]
+fn write_yuv_from_packed(
+    data: &[u8],
+    dimensions: (usize, usize),
+    stride: usize,
+    offsets: [usize; 4],
+    (y_buf, y_stride): (&mut [u8], usize),
+    u_buf: &mut [u8],
+    v_buf: &mut [u8],
+) {
+    let (width, height) = dimensions;
+    let chroma_width = width.div_ceil(2);
+    let [y0, u, y1, v] = offsets;
+    let rows = data.chunks(stride).map(|row| &row[..row_len(width)]).take(height);
+
+    // `Y` is either the first or the second byte of every 16 bit pair.
+    let y_shift = lane_shift(y0 % 2, 2);
+    let simd_width = width - width % 16;
+
+    for (row, y_row) in rows.clone().zip(y_buf.chunks_exact_mut(y_stride)) {
+        let (simd_row, row) = row.split_at(simd_width * 2);
+        let (simd_y_row, y_row) = y_row[..width].split_at_mut(simd_width);
+
+        for (pixels, y) in simd_row.chunks_exact(32).zip(simd_y_row.chunks_exact_mut(16)) {
+            y.copy_from_slice(&luma_simd(pixels, y_shift));
+        }
+
+        let mut y_pairs = y_row.chunks_exact_mut(2);
+
+        for (pixels, y) in row.chunks_exact(4).zip(&mut y_pairs) {
+            y[0] = pixels[y0];
+            y[1] = pixels[y1];
+        }
+
+        // Odd widths only use `Y0` of the last macropixel.
+        if let [y] = y_pairs.into_remainder() {
+            *y = row[row.len() - 4 + y0];
+        }
+    }
+
+    let u_rows = u_buf.chunks_exact_mut(chroma_width);
+    let v_rows = v_buf.chunks_exact_mut(chroma_width);
+
+    // For odd heights the last row is paired with itself.
+    let r1 = rows.clone().step_by(2);
+    let r2 = rows
+        .clone()
+        .skip(1)
+        .step_by(2)
+        .chain(rows.clone().next_back().filter(|_| height % 2 == 1));
+
+    let (u_shift, v_shift) = (lane_shift(u, 4), lane_shift(v, 4));
+    let simd_chroma_width = chroma_width - chroma_width % 16;
+
+    for (((r1, r2), u_row), v_row) in r1.zip(r2).zip(u_rows).zip(v_rows) {
+        let (simd_r1, r1) = r1.split_at(simd_chroma_width * 4);
+        let (simd_r2, r2) = r2.split_at(simd_chroma_width * 4);
+        let (simd_u_row, u_row) = u_row.split_at_mut(simd_chroma_width);
+        let (simd_v_row, v_row) = v_row.split_at_mut(simd_chroma_width);
+
+        for (((pix0, pix1), u_out), v_out) in simd_r1
+            .chunks_exact(64)
+            .zip(simd_r2.chunks_exact(64))
+            .zip(simd_u_row.chunks_exact_mut(16))
+            .zip(simd_v_row.chunks_exact_mut(16))
+        {
+            u_out.copy_from_slice(&chroma_simd(pix0, pix1, u_shift));
+            v_out.copy_from_slice(&chroma_simd(pix0, pix1, v_shift));
+        }
+
+        for (((pix0, pix1), u_out), v_out) in r1.chunks_exact(4).zip(r2.chunks_exact(4)).zip(u_row).zip(v_row) {
+            *u_out = (u16::from(pix0[u]) + u16::from(pix1[u])).div_ceil(2) as u8;
+            *v_out = (u16::from(pix0[v]) + u16::from(pix1[v])).div_ceil(2) as u8;
+        }
+    }
+}
+
+/// Right shift moving byte `offset` of a lane of `size` bytes loaded from memory into its lowest byte.
+const fn lane_shift(offset: usize, size: usize) -> u32 {
+    if cfg!(target_endian = "little") {
+        offset as u32 * 8
+    } else {
+        (size - 1 - offset) as u32 * 8
+    }
+}
+
+/// Extracts the 16 luma samples of 8 macropixels, shifting each 16 bit pair right by `y_shift` first.
+#[allow(clippy::inline_always)]
+#[inline(always)]
+fn luma_simd(pixels: &[u8], y_shift: u32) -> [u8; 16] {
+    let low_byte = i16x8::splat(0xff);
+    let lanes = |pixels: &[u8]| (cast::<[u8; 16], i16x8>(pixels.try_into().expect("16 bytes")) >> y_shift) & low_byte;
+
+    u8x16::narrow_i16x8(lanes(&pixels[..16]), lanes(&pixels[16..])).to_array()
+}
+
+/// Averages one chroma sample of 16 macropixels from two rows, shifting each macropixel right by `shift` first.
+#[allow(clippy::inline_always)]
+#[inline(always)]
+fn chroma_simd(row0: &[u8], row1: &[u8], shift: u32) -> [u8; 16] {
+    let low_byte = i32x8::splat(0xff);
+    let lanes = |pixels: &[u8]| (cast::<[u8; 32], i32x8>(pixels.try_into().expect("32 bytes")) >> shift) & low_byte;
+    let average = |a: i32x8, b: i32x8| i16x8::from_i32x8_saturate((a + b + i32x8::splat(1)) >> 1);
+
+    let first = average(lanes(&row0[..32]), lanes(&row1[..32]));
+    let second = average(lanes(&row0[32..]), lanes(&row1[32..]));
+
+    u8x16::narrow_i16x8(first, second).to_array()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{UyvySliceU8, YuyvSliceU8, YvyuSliceU8};
+    use crate::formats::EncoderInput;
+
+    #[test]
+    fn packed_to_yuv_averages_rows() {
+        let yuyv = [1u8, 10, 2, 20, 3, 11, 4, 21, 5, 12, 6, 22, 7, 14, 8, 23];
+        let uyvy = [10u8, 1, 20, 2, 11, 3, 21, 4, 12, 5, 22, 6, 14, 7, 23, 8];
+        let yvyu = [1u8, 20, 2, 10, 3, 21, 4, 11, 5, 22, 6, 12, 7, 23, 8, 14];
+
+        let (yuyv, uyvy, yvyu) = (
+            YuyvSliceU8::new(&yuyv, (4, 2)),
+            UyvySliceU8::new(&uyvy, (4, 2)),
+            YvyuSliceU8::new(&yvyu, (4, 2)),
+        );
+        let (mut b1, mut b2, mut b3) = (None, None, None);
+
+        for yuv in [yuyv.as_yuv(&mut b1), uyvy.as_yuv(&mut b2), yvyu.as_yuv(&mut b3)] {
+            assert_eq!(yuv.dimensions(), (4, 2));
+            assert_eq!(yuv.y(), [1u8, 2, 3, 4, 5, 6, 7, 8]);
+            assert_eq!(yuv.u(), [11u8, 13]);
+            assert_eq!(yuv.v(), [21u8, 22]);
+        }
+    }
+
+    #[test]
+    fn packed_to_yuv_odd_height() {
+        let data = [1u8, 10, 2, 20, 3, 12, 4, 22, 5, 30, 6, 40];
+        let source = YuyvSliceU8::new(&data, (2, 3));
+        let mut buffer = None;
+        let yuv = source.as_yuv(&mut buffer);
+
+        assert_eq!(yuv.dimensions(), (2, 4));
+        assert_eq!(yuv.y(), [1u8, 2, 3, 4, 5, 6, 5, 6]);
+        assert_eq!(yuv.u(), [11u8, 30]);
+        assert_eq!(yuv.v(), [21u8, 40]);
+    }
+
+    #[test]
+    fn packed_to_yuv_with_stride_odd_width() {
+        let data = [1u8, 10, 2, 20, 3, 11, 99, 21, 0, 0, 4, 12, 5, 22, 6, 13, 99, 23];
+        let source = YuyvSliceU8::with_stride(&data, (3, 2), 10);
+        let mut buffer = None;
+        let yuv = source.as_yuv(&mut buffer);
+
+        assert_eq!(yuv.dimensions(), (4, 2));
+        assert_eq!(yuv.y(), [1u8, 2, 3, 3, 4, 5, 6, 6]);
+        assert_eq!(yuv.u(), [11u8, 12]);
+        assert_eq!(yuv.v(), [21u8, 22]);
+    }
+
+    #[test]
+    fn packed_to_yuv_simd_matches_layout() {
+        for (w, h) in [(32_usize, 2_usize), (67, 3), (50, 4)] {
+            let stride = w.div_ceil(2) * 4 + 8;
+            let data = (0..stride * h).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+            let byte = |x: usize, y: usize, offset: usize| data[y.min(h - 1) * stride + x * 4 + offset];
+
+            // Odd sizes are padded by repeating the last column and row.
+            let expected = |[y0, u, y1, v]: [usize; 4]| {
+                let luma = (0..h.next_multiple_of(2))
+                    .flat_map(|y| (0..w.next_multiple_of(2)).map(move |x| x.min(w - 1)).map(move |x| (x, y)))
+                    .map(|(x, y)| byte(x / 2, y, [y0, y1][x % 2]))
+                    .collect::<Vec<_>>();
+                let chroma = |offset: usize| {
+                    (0..h.div_ceil(2))
+                        .flat_map(|y| (0..w.div_ceil(2)).map(move |x| (x, y)))
+                        .map(|(x, y)| {
+                            (u16::from(byte(x, 2 * y, offset)) + u16::from(byte(x, 2 * y + 1, offset))).div_ceil(2) as u8
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+                (luma, chroma(u), chroma(v))
+            };
+            let (mut b1, mut b2, mut b3) = (None, None, None);
+
+            for (yuv, offsets) in [
+                (YuyvSliceU8::with_stride(&data, (w, h), stride).as_yuv(&mut b1), [0, 1, 2, 3]),
+                (UyvySliceU8::with_stride(&data, (w, h), stride).as_yuv(&mut b2), [1, 0, 3, 2]),
+                (YvyuSliceU8::with_stride(&data, (w, h), stride).as_yuv(&mut b3), [0, 3, 2, 1]),
+            ] {
+                assert_eq!((yuv.y().to_vec(), yuv.u().to_vec(), yuv.v().to_vec()), expected(offsets));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic = "stride >= row_len(dimensions.0)"]
+    fn packed_stride_too_small() {
+        let data = [0u8; 16];
+        let _ = UyvySliceU8::with_stride(&data, (3, 2), 6);
+    }
+}
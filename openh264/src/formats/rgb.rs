@@ -20,10 +20,61 @@ pub trait RGBSource {
     fn pixel_f32(&self, x: usize, y: usize) -> (f32, f32, f32);
 }
 
-/// Source of RGB8 data for fast pixel access.
+/// Byte order of the channels in a packed 8 bit RGB pixel, as returned by [`RGB8Source::layout()`] and
+/// written by [`YUVSource::write_rgb8_layout()`](crate::formats::YUVSource::write_rgb8_layout).
+///
+/// Alpha channels are ignored when reading, and set to 255 when writing.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RGB8Layout {
+    /// `[R G B R G B ...]`
+    #[default]
+    Rgb,
+    /// `[B G R B G R ...]`
+    Bgr,
+    /// `[R G B A R G B A ...]`
+    Rgba,
+    /// `[B G R A B G R A ...]`
+    Bgra,
+    /// `[A R G B A R G B ...]`
+    Argb,
+    /// `[A B G R A B G R ...]`
+    Abgr,
+}
+
+impl RGB8Layout {
+    /// Number of bytes per pixel.
+    #[must_use]
+    pub const fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb | Self::Bgr => 3,
+            Self::Rgba | Self::Bgra | Self::Argb | Self::Abgr => 4,
+        }
+    }
+
+    /// Layout of `u32` pixels given by the shift of their R, G and B channels, as stored in memory.
+    const fn from_u32_shifts(shifts: [u32; 3]) -> Self {
+        // Position of the R and B bytes within each pixel in memory.
+        let (r, b) = if cfg!(target_endian = "little") {
+            (shifts[0] / 8, shifts[2] / 8)
+        } else {
+            (3 - shifts[0] / 8, 3 - shifts[2] / 8)
+        };
+
+        match (r, b) {
+            (0, 2) => Self::Rgba,
+            (2, 0) => Self::Bgra,
+            (1, 3) => Self::Argb,
+            (3, 1) => Self::Abgr,
+            _ => panic!("unsupported u32 channel layout"),
+        }
+    }
+}
+
+/// Source of packed 8 bit RGB data for fast pixel access.
 ///
 /// This is the "fast" trait for RGB sources. If you can expose continuous pixels
-/// slices with RGB8 data you might (eventually) be rewarded with SIMD conversion.
+/// slices with 8 bit channels in one of the [`RGB8Layout`]s you will be rewarded with
+/// SIMD conversion.
 pub trait RGB8Source: RGBSource {
     /// Returns padded dimensions of the underlying slice.
     ///
@@ -32,9 +83,24 @@ pub trait RGB8Source: RGBSource {
     #[must_use]
     fn dimensions_padded(&self) -> (usize, usize);
 
-    /// Slice of RGB8 data, with given padding.
+    /// Slice of pixel data in the given [`layout`](Self::layout), with given padding.
     #[must_use]
     fn rgb8_data(&self) -> &[u8];
+
+    /// Byte order of the pixels in [`rgb8_data`](Self::rgb8_data), `[R G B ...]` by default.
+    #[must_use]
+    fn layout(&self) -> RGB8Layout {
+        RGB8Layout::Rgb
+    }
+
+    /// Distance between the start of two rows in [`rgb8_data`](Self::rgb8_data) in bytes.
+    ///
+    /// Defaults to the padded width times the size of a pixel. Override this if your rows are
+    /// aligned to a number of bytes that is not a multiple of the pixel size.
+    #[must_use]
+    fn row_stride(&self) -> usize {
+        self.dimensions_padded().0 * self.layout().bytes_per_pixel()
+    }
 }
 
 /// Container for a slice of contiguous `[R G B R G B ...]` data.<sup>⭐</sup>
@@ -45,6 +111,7 @@ pub trait RGB8Source: RGBSource {
 pub struct RgbSliceU8<'a> {
     data: &'a [u8],
     dimensions: (usize, usize),
+    stride: usize,
 }
 
 /// Container for a slice of contiguous `[B G R B G R ...]` data.
@@ -53,6 +120,7 @@ pub struct RgbSliceU8<'a> {
 pub struct BgrSliceU8<'a> {
     data: &'a [u8],
     dimensions: (usize, usize),
+    stride: usize,
 }
 
 /// Container for a slice of contiguous `[R G B A R G B A ...]` data.
@@ -61,6 +129,7 @@ pub struct BgrSliceU8<'a> {
 pub struct RgbaSliceU8<'a> {
     data: &'a [u8],
     dimensions: (usize, usize),
+    stride: usize,
 }
 
 /// Container for a slice of contiguous `[RGBA RGBA ...]` data.
@@ -71,6 +140,7 @@ pub struct RgbaSliceU8<'a> {
 pub struct RgbaSliceU32<'a> {
     data: &'a [u32],
     dimensions: (usize, usize),
+    stride: usize,
 }
 
 /// Container for a slice of contiguous `[B G R A B G R A ...]` data.
@@ -79,6 +149,7 @@ pub struct RgbaSliceU32<'a> {
 pub struct BgraSliceU8<'a> {
     data: &'a [u8],
     dimensions: (usize, usize),
+    stride: usize,
 }
 
 /// Container for a slice of contiguous `[BGRA BGRA ...]` data.
@@ -89,6 +160,7 @@ pub struct BgraSliceU8<'a> {
 pub struct BgraSliceU32<'a> {
     data: &'a [u32],
     dimensions: (usize, usize),
+    stride: usize,
 }
 
 /// Container for a slice of contiguous `[A B G R A B G R ...]` data.
@@ -97,6 +169,7 @@ pub struct BgraSliceU32<'a> {
 pub struct AbgrSliceU8<'a> {
     data: &'a [u8],
     dimensions: (usize, usize),
+    stride: usize,
 }
 
 /// Container for a slice of contiguous `[ABGR ABGR ...]` data.
@@ -107,6 +180,7 @@ pub struct AbgrSliceU8<'a> {
 pub struct AbgrSliceU32<'a> {
     data: &'a [u32],
     dimensions: (usize, usize),
+    stride: usize,
 }
 
 /// Container for a slice of contiguous `[A R G B A R G B ...]` data.
@@ -115,6 +189,7 @@ pub struct AbgrSliceU32<'a> {
 pub struct ArgbSliceU8<'a> {
     data: &'a [u8],
     dimensions: (usize, usize),
+    stride: usize,
 }
 
 /// Container for a slice of contiguous `[ARGB ARGB ...]` data.
@@ -125,23 +200,41 @@ pub struct ArgbSliceU8<'a> {
 pub struct ArgbSliceU32<'a> {
     data: &'a [u32],
     dimensions: (usize, usize),
+    stride: usize,
 }
 
 macro_rules! impl_slice_wrapper_u8 {
-    ($t:ty, $stride:expr, $offsets:expr) => {
+    ($t:ty, $pixel_len:expr, $offsets:expr, $layout:expr) => {
         impl<'a> $t {
             /// Creates a new instance given the byte slice and dimensions.
             ///
             /// # Panics
             ///
-            /// May panic if the given sizes are not multiples of 2, or if the slice length mismatches the given dimensions.
+            /// May panic if the slice length mismatches the given dimensions.
             #[allow(unused)]
             pub fn new(data: &'a [u8], dimensions: (usize, usize)) -> Self {
-                assert_eq!(data.len(), dimensions.0 * dimensions.1 * $stride);
-                assert_eq!(dimensions.0 % 2, 0, "width needs to be multiple of 2");
-                assert_eq!(dimensions.1 % 2, 0, "height needs to be a multiple of 2");
+                assert_eq!(data.len(), dimensions.0 * dimensions.1 * $pixel_len);
 
-                Self { data, dimensions }
+                Self::with_stride(data, dimensions, dimensions.0 * $pixel_len)
+            }
+
+            /// Creates a new instance given the byte slice, dimensions and the distance between rows in bytes.
+            ///
+            /// The last row does not need to be padded to the full stride.
+            ///
+            /// # Panics
+            ///
+            /// May panic if the stride is smaller than a row, or if the slice is too short for the given dimensions.
+            #[allow(unused)]
+            pub fn with_stride(data: &'a [u8], dimensions: (usize, usize), stride: usize) -> Self {
+                assert!(stride >= dimensions.0 * $pixel_len);
+                assert!(data.len() >= min_len(dimensions, stride, $pixel_len));
+
+                Self {
+                    data,
+                    dimensions,
+                    stride,
+                }
             }
         }
 
@@ -151,7 +244,7 @@ macro_rules! impl_slice_wrapper_u8 {
             }
 
             fn pixel_f32(&self, x: usize, y: usize) -> (f32, f32, f32) {
-                let base_pos = (x + y * self.dimensions.0) * $stride;
+                let base_pos = y * self.stride + x * $pixel_len;
                 (
                     self.data[base_pos + $offsets[0]].into(),
                     self.data[base_pos + $offsets[1]].into(),
@@ -159,6 +252,24 @@ macro_rules! impl_slice_wrapper_u8 {
                 )
             }
         }
+
+        impl<'a> RGB8Source for $t {
+            fn dimensions_padded(&self) -> (usize, usize) {
+                (self.stride / $pixel_len, self.dimensions.1)
+            }
+
+            fn rgb8_data(&self) -> &[u8] {
+                self.data
+            }
+
+            fn layout(&self) -> RGB8Layout {
+                $layout
+            }
+
+            fn row_stride(&self) -> usize {
+                self.stride
+            }
+        }
     };
 }
 
@@ -169,14 +280,33 @@ macro_rules! impl_slice_wrapper_u32 {
             ///
             /// # Panics
             ///
-            /// May panic if the given sizes are not multiples of 2, or if the slice length mismatches the given dimensions.
+            /// May panic if the slice length mismatches the given dimensions.
             #[allow(unused)]
             pub fn new(data: &'a [u32], dimensions: (usize, usize)) -> Self {
                 assert_eq!(data.len(), dimensions.0 * dimensions.1);
-                assert_eq!(dimensions.0 % 2, 0, "width needs to be multiple of 2");
-                assert_eq!(dimensions.1 % 2, 0, "height needs to be a multiple of 2");
 
-                Self { data, dimensions }
+                Self::with_stride(data, dimensions, dimensions.0 * 4)
+            }
+
+            /// Creates a new instance given the data slice, dimensions and the distance between rows in bytes.
+            ///
+            /// The last row does not need to be padded to the full stride.
+            ///
+            /// # Panics
+            ///
+            /// May panic if the stride is not a multiple of 4 or smaller than a row, or if the slice is too short for
+            /// the given dimensions.
+            #[allow(unused)]
+            pub fn with_stride(data: &'a [u32], dimensions: (usize, usize), stride: usize) -> Self {
+                assert_eq!(stride % 4, 0, "stride needs to be a multiple of 4");
+                assert!(stride >= dimensions.0 * 4);
+                assert!(size_of_val(data) >= min_len(dimensions, stride, 4));
+
+                Self {
+                    data,
+                    dimensions,
+                    stride,
+                }
             }
         }
 
@@ -186,7 +316,7 @@ macro_rules! impl_slice_wrapper_u32 {
             }
 
             fn pixel_f32(&self, x: usize, y: usize) -> (f32, f32, f32) {
-                let px = self.data[x + y * self.dimensions.0];
+                let px = self.data[x + y * self.stride / 4];
                 (
                     ((px >> $offsets[0]) & 0xFF) as f32,
                     ((px >> $offsets[1]) & 0xFF) as f32,
@@ -194,34 +324,51 @@ macro_rules! impl_slice_wrapper_u32 {
                 )
             }
         }
+
+        impl<'a> RGB8Source for $t {
+            fn dimensions_padded(&self) -> (usize, usize) {
+                (self.stride / 4, self.dimensions.1)
+            }
+
+            fn rgb8_data(&self) -> &[u8] {
+                // SAFETY: `u8` has no alignment requirements, and every byte of a `u32` slice is initialized.
+                unsafe { std::slice::from_raw_parts(self.data.as_ptr().cast::<u8>(), size_of_val(self.data)) }
+            }
+
+            fn layout(&self) -> RGB8Layout {
+                const { RGB8Layout::from_u32_shifts($offsets) }
+            }
+
+            fn row_stride(&self) -> usize {
+                self.stride
+            }
+        }
     };
 }
 
-impl_slice_wrapper_u8!(RgbSliceU8<'a>, 3, [0, 1, 2]);
-impl_slice_wrapper_u8!(RgbaSliceU8<'a>, 4, [0, 1, 2]);
-impl_slice_wrapper_u8!(BgrSliceU8<'a>, 3, [2, 1, 0]);
-impl_slice_wrapper_u8!(BgraSliceU8<'a>, 4, [2, 1, 0]);
-impl_slice_wrapper_u8!(ArgbSliceU8<'a>, 4, [1, 2, 3]);
-impl_slice_wrapper_u8!(AbgrSliceU8<'a>, 4, [3, 2, 1]);
+/// Minimum number of bytes needed for an image with the given row stride, where the last row need not be padded.
+const fn min_len(dimensions: (usize, usize), stride: usize, pixel_len: usize) -> usize {
+    match dimensions.1 {
+        0 => 0,
+        h => (h - 1) * stride + dimensions.0 * pixel_len,
+    }
+}
+
+impl_slice_wrapper_u8!(RgbSliceU8<'a>, 3, [0, 1, 2], RGB8Layout::Rgb);
+impl_slice_wrapper_u8!(RgbaSliceU8<'a>, 4, [0, 1, 2], RGB8Layout::Rgba);
+impl_slice_wrapper_u8!(BgrSliceU8<'a>, 3, [2, 1, 0], RGB8Layout::Bgr);
+impl_slice_wrapper_u8!(BgraSliceU8<'a>, 4, [2, 1, 0], RGB8Layout::Bgra);
+impl_slice_wrapper_u8!(ArgbSliceU8<'a>, 4, [1, 2, 3], RGB8Layout::Argb);
+impl_slice_wrapper_u8!(AbgrSliceU8<'a>, 4, [3, 2, 1], RGB8Layout::Abgr);
 
 impl_slice_wrapper_u32!(RgbaSliceU32<'a>, [24, 16, 8]);
 impl_slice_wrapper_u32!(BgraSliceU32<'a>, [8, 16, 24]);
 impl_slice_wrapper_u32!(AbgrSliceU32<'a>, [0, 8, 16]);
 impl_slice_wrapper_u32!(ArgbSliceU32<'a>, [16, 8, 0]);
 
-impl RGB8Source for RgbSliceU8<'_> {
-    fn dimensions_padded(&self) -> (usize, usize) {
-        self.dimensions()
-    }
-
-    fn rgb8_data(&self) -> &[u8] {
-        self.data
-    }
-}
-
 #[cfg(test)]
 mod tests {
-    use super::{AbgrSliceU32, ArgbSliceU32, BgrSliceU8, BgraSliceU32, RGBSource, RgbSliceU8, RgbaSliceU32};
+    use super::{AbgrSliceU32, ArgbSliceU32, BgrSliceU8, BgraSliceU32, RGB8Source, RGBSource, RgbSliceU8, RgbaSliceU32};
 
     #[test]
     fn rgb_slice_4x4() {
@@ -292,4 +439,26 @@ mod tests {
         assert_eq!(slice.pixel_f32(0, 1), (1., 0., 2.));
         assert_eq!(slice.pixel_f32(1, 1), (221., 204., 187.));
     }
+
+    #[test]
+    fn slices_with_stride() {
+        let data: Vec<u8> = (0..2 * 8 + 6).collect();
+        let slice = RgbSliceU8::with_stride(&data, (2, 3), 8);
+        assert_eq!(slice.pixel_f32(1, 0), (3., 4., 5.));
+        assert_eq!(slice.pixel_f32(0, 1), (8., 9., 10.));
+        assert_eq!(slice.pixel_f32(1, 2), (19., 20., 21.));
+        assert_eq!(slice.row_stride(), 8);
+
+        let data: [u32; 6] = [0xFF000102, 0xFF010002, 0, 0, 0xFF000201, 0xAABBCCDD];
+        let slice = RgbaSliceU32::with_stride(&data, (2, 2), 16);
+        assert_eq!(slice.pixel_f32(0, 1), (255., 0., 2.));
+        assert_eq!(slice.pixel_f32(1, 1), (170., 187., 204.));
+    }
+
+    #[test]
+    #[should_panic = "stride >= dimensions.0 * 3"]
+    fn stride_smaller_than_row() {
+        let data = [0u8; 12];
+        let _ = RgbSliceU8::with_stride(&data, (2, 2), 5);
+    }
 }
@@ -1,37 +1,83 @@
-use crate::formats::RGBSource;
-use crate::formats::rgb::RGB8Source;
+use crate::formats::rgb::{RGB8Layout, RGB8Source};
+use crate::formats::{ColorMatrix, ColorSpace, RGBSource};
+use std::array;
+use wide::bytemuck::cast;
+use wide::{i16x8, i16x16, i32x8, u8x16};
+
+/// Weights converting RGB into YUV of a given [`ColorSpace`], as 8 bit fixed point numbers.
+///
+/// Each row holds the weights of R, G and B. Luma weights add up to 220 (limited range) or 256 (full range),
+/// chroma weights add up to 0 so grays have no chroma.
+#[derive(Copy, Clone, Debug)]
+struct RgbToYuv {
+    y: [i16; 3],
+    u: [i16; 3],
+    v: [i16; 3],
+    y_offset: i16,
+}
+
+impl RgbToYuv {
+    // https://en.wikipedia.org/wiki/YCbCr#ITU-R_BT.601_conversion
+    const fn new(color_space: ColorSpace) -> Self {
+        let (y, u, v) = match (color_space.color_matrix(), color_space.is_full_range()) {
+            (ColorMatrix::Bt601, false) => ([66, 129, 25], [-38, -74, 112], [112, -94, -18]),
+            (ColorMatrix::Bt601, true) => ([77, 150, 29], [-43, -85, 128], [128, -107, -21]),
+            (ColorMatrix::Bt709, false) => ([47, 157, 16], [-26, -86, 112], [112, -102, -10]),
+            (ColorMatrix::Bt709, true) => ([54, 183, 19], [-29, -99, 128], [128, -116, -12]),
+            (ColorMatrix::Bt2020, false) => ([58, 149, 13], [-31, -81, 112], [112, -103, -9]),
+            (ColorMatrix::Bt2020, true) => ([67, 174, 15], [-36, -92, 128], [128, -118, -10]),
+        };
+        let y_offset = if color_space.is_full_range() { 0 } else { 16 };
+
+        Self { y, u, v, y_offset }
+    }
+}
 
 /// Writes an RGB source into 420 Y, U and V buffers.
 #[allow(clippy::needless_pass_by_value)]
-pub fn write_yuv_by_pixel(rgb: impl RGBSource, dimensions: (usize, usize), y_buf: &mut [u8], u_buf: &mut [u8], v_buf: &mut [u8]) {
+pub fn write_yuv_by_pixel(
+    rgb: impl RGBSource,
+    dimensions: (usize, usize),
+    color_space: ColorSpace,
+    y_buf: &mut [u8],
+    u_buf: &mut [u8],
+    v_buf: &mut [u8],
+) {
     // Make sure we only attempt to read sources that match our own size.
     assert_eq!(rgb.dimensions(), dimensions);
 
     let width = dimensions.0;
     let height = dimensions.1;
-    let half_width = width / 2;
+    let half_width = width.div_ceil(2);
+
+    let RgbToYuv { y, u, v, y_offset } = RgbToYuv::new(color_space);
+    let [y, u, v] = [y, u, v].map(|weights| weights.map(|w| f32::from(w) / 256.0));
+    let y_offset = f32::from(y_offset);
 
     // y is full size, u, v is quarter size
-    let mut write_y = |x: usize, y: usize, rgb: (f32, f32, f32)| {
-        y_buf[x + y * width] = (0.09765625f32.mul_add(rgb.2, 0.2578125f32.mul_add(rgb.0, 0.50390625 * rgb.1)) + 16.0) as u8;
+    let mut write_y = |x: usize, py: usize, rgb: (f32, f32, f32)| {
+        y_buf[x + py * width] = (y[2].mul_add(rgb.2, y[0].mul_add(rgb.0, y[1] * rgb.1)) + y_offset) as u8;
     };
 
-    let mut write_u = |x: usize, y: usize, rgb: (f32, f32, f32)| {
-        u_buf[x + y * half_width] = (0.4375f32.mul_add(rgb.2, (-0.1484375f32).mul_add(rgb.0, -0.2890625 * rgb.1)) + 128.0) as u8;
+    let mut write_u = |x: usize, py: usize, rgb: (f32, f32, f32)| {
+        u_buf[x + py * half_width] = (u[2].mul_add(rgb.2, u[0].mul_add(rgb.0, u[1] * rgb.1)) + 128.0) as u8;
     };
 
-    let mut write_v = |x: usize, y: usize, rgb: (f32, f32, f32)| {
-        v_buf[x + y * half_width] = ((-0.0703125f32).mul_add(rgb.2, 0.4375f32.mul_add(rgb.0, -0.3671875 * rgb.1)) + 128.0) as u8;
+    let mut write_v = |x: usize, py: usize, rgb: (f32, f32, f32)| {
+        v_buf[x + py * half_width] = (v[2].mul_add(rgb.2, v[0].mul_add(rgb.0, v[1] * rgb.1)) + 128.0) as u8;
     };
 
-    for i in 0..width / 2 {
-        for j in 0..height / 2 {
+    for i in 0..half_width {
+        for j in 0..height.div_ceil(2) {
             let px = i * 2;
             let py = j * 2;
+            // For odd sizes the last column or row has no neighbor, so it is averaged with itself.
+            let px1 = (px + 1).min(width - 1);
+            let py1 = (py + 1).min(height - 1);
             let pix0x0 = rgb.pixel_f32(px, py);
-            let pix0x1 = rgb.pixel_f32(px, py + 1);
-            let pix1x0 = rgb.pixel_f32(px + 1, py);
-            let pix1x1 = rgb.pixel_f32(px + 1, py + 1);
+            let pix0x1 = rgb.pixel_f32(px, py1);
+            let pix1x0 = rgb.pixel_f32(px1, py);
+            let pix1x1 = rgb.pixel_f32(px1, py1);
             let avg_pix = (
                 (pix0x0.0 as u32 + pix0x1.0 as u32 + pix1x0.0 as u32 + pix1x1.0 as u32) as f32 / 4.0,
                 (pix0x0.1 as u32 + pix0x1.1 as u32 + pix1x0.1 as u32 + pix1x1.1 as u32) as f32 / 4.0,
@@ -39,56 +85,266 @@ pub fn write_yuv_by_pixel(rgb: impl RGBSource, dimensions: (usize, usize), y_buf
             );
 
             write_y(px, py, pix0x0);
-            write_y(px, py + 1, pix0x1);
-            write_y(px + 1, py, pix1x0);
-            write_y(px + 1, py + 1, pix1x1);
+            write_y(px, py1, pix0x1);
+            write_y(px1, py, pix1x0);
+            write_y(px1, py1, pix1x1);
             write_u(i, j, avg_pix);
             write_v(i, j, avg_pix);
         }
     }
 }
 
-/// Writes an RGB8 source into 420 Y, U and V buffers.
+/// Calls `$f::<PIXEL_LEN, R, G, B>($args)` with the pixel size and channel offsets of the given layout.
+macro_rules! with_layout {
+    ($layout:expr, $f:ident($($arg:expr),*)) => {
+        match $layout {
+            RGB8Layout::Rgb => $f::<3, 0, 1, 2>($($arg),*),
+            RGB8Layout::Bgr => $f::<3, 2, 1, 0>($($arg),*),
+            RGB8Layout::Rgba => $f::<4, 0, 1, 2>($($arg),*),
+            RGB8Layout::Bgra => $f::<4, 2, 1, 0>($($arg),*),
+            RGB8Layout::Argb => $f::<4, 1, 2, 3>($($arg),*),
+            RGB8Layout::Abgr => $f::<4, 3, 2, 1>($($arg),*),
+        }
+    };
+}
+
+/// Writes an RGB8 source into 420 Y, U and V buffers using scalar (non SIMD) math.
+#[allow(clippy::needless_pass_by_value)]
+pub fn write_yuv_scalar(
+    rgb: impl RGB8Source,
+    dimensions: (usize, usize),
+    color_space: ColorSpace,
+    y_buf: &mut [u8],
+    u_buf: &mut [u8],
+    v_buf: &mut [u8],
+) {
+    // Make sure we only attempt to read sources that match our own size.
+    assert_eq!(rgb.dimensions(), dimensions);
+
+    let layout = rgb.layout();
+    let row_stride = rgb.row_stride();
+    let weights = RgbToYuv::new(color_space);
+    with_layout!(
+        layout,
+        write_yuv_packed_scalar(rgb.rgb8_data(), row_stride, dimensions, weights, y_buf, u_buf, v_buf)
+    );
+}
+
+/// Writes an RGB8 source into 420 Y, U and V buffers using SIMD.
 ///
-/// TODO: We want a faster SIMD version of this.
+/// The results are identical to [`write_yuv_scalar`].
 #[allow(clippy::needless_pass_by_value)]
-pub fn write_yuv_scalar(rgb: impl RGB8Source, dimensions: (usize, usize), y_buf: &mut [u8], u_buf: &mut [u8], v_buf: &mut [u8]) {
+pub fn write_yuv_simd(
+    rgb: impl RGB8Source,
+    dimensions: (usize, usize),
+    color_space: ColorSpace,
+    y_buf: &mut [u8],
+    u_buf: &mut [u8],
+    v_buf: &mut [u8],
+) {
     // Make sure we only attempt to read sources that match our own size.
     assert_eq!(rgb.dimensions(), dimensions);
 
-    let dimensions_padded = rgb.dimensions_padded();
-    let width = dimensions.0;
+    let layout = rgb.layout();
+    let row_stride = rgb.row_stride();
+    let weights = RgbToYuv::new(color_space);
+    with_layout!(
+        layout,
+        write_yuv_packed_simd(rgb.rgb8_data(), row_stride, dimensions, weights, y_buf, u_buf, v_buf)
+    );
+}
+
+/// Returns the visible part of each row of `data`, a buffer of `PIXEL_LEN` byte pixels with rows `row_stride` bytes apart.
+fn packed_rows<const PIXEL_LEN: usize>(
+    data: &[u8],
+    row_stride: usize,
+    dimensions: (usize, usize),
+) -> impl DoubleEndedIterator<Item = &[u8]> + Clone {
+    data.chunks(row_stride)
+        .take(dimensions.1)
+        .map(move |row| &row[..dimensions.0 * PIXEL_LEN])
+}
 
-    let half_width = width / 2;
-    let rgb8_data = rgb.rgb8_data();
+/// Converts pixels of `PIXEL_LEN` bytes each, with channels at byte offsets `R`, `G` and `B`, into 420 Y, U and V buffers.
+fn write_yuv_packed_scalar<const PIXEL_LEN: usize, const R: usize, const G: usize, const B: usize>(
+    data: &[u8],
+    row_stride: usize,
+    dimensions: (usize, usize),
+    weights: RgbToYuv,
+    y_buf: &mut [u8],
+    u_buf: &mut [u8],
+    v_buf: &mut [u8],
+) {
+    let (width, height) = dimensions;
+    let half_width = width.div_ceil(2);
+    let rows = packed_rows::<PIXEL_LEN>(data, row_stride, dimensions);
 
-    for (pix, y) in rgb8_data.chunks_exact(3).zip(y_buf.iter_mut()) {
-        *y = (((66 * u32::from(pix[0]) + 129 * u32::from(pix[1]) + 25 * u32::from(pix[2])) >> 8) + 16) as u8;
+    for (row, y_row) in rows.clone().zip(y_buf.chunks_exact_mut(width)) {
+        for (pix, y) in row.chunks_exact(PIXEL_LEN).zip(y_row) {
+            let luma = i32::from(weights.y[0]) * i32::from(pix[R])
+                + i32::from(weights.y[1]) * i32::from(pix[G])
+                + i32::from(weights.y[2]) * i32::from(pix[B]);
+            *y = ((luma >> 8) + i32::from(weights.y_offset)) as u8;
+        }
     }
 
-    let r1 = rgb8_data.chunks_exact(dimensions_padded.0 * 3).step_by(2);
-    let r2 = rgb8_data.chunks_exact(dimensions_padded.0 * 3).skip(1).step_by(2);
+    // For odd heights the last row is paired with itself.
+    let r1 = rows.clone().step_by(2);
+    let r2 = rows.clone().skip(1).step_by(2).chain(rows.last().filter(|_| height % 2 == 1));
 
     let u_rows = u_buf.chunks_exact_mut(half_width);
     let v_rows = v_buf.chunks_exact_mut(half_width);
     for (((r1, r2), u), v) in r1.zip(r2).zip(u_rows).zip(v_rows) {
-        for (((pix0, pix1), u), v) in r1.chunks_exact(2 * 3).zip(r2.chunks_exact(2 * 3)).zip(u).zip(v) {
-            let r = (i16::from(pix0[0]) + i16::from(pix0[3]) + i16::from(pix1[0]) + i16::from(pix1[3]) + 2) / 4;
-            let g = (i16::from(pix0[1]) + i16::from(pix0[4]) + i16::from(pix1[1]) + i16::from(pix1[4]) + 2) / 4;
-            let b = (i16::from(pix0[2]) + i16::from(pix0[5]) + i16::from(pix1[2]) + i16::from(pix1[5]) + 2) / 4;
+        for (((pix0, pix1), u), v) in r1.chunks(2 * PIXEL_LEN).zip(r2.chunks(2 * PIXEL_LEN)).zip(u).zip(v) {
+            // For odd widths the last pixel is paired with itself.
+            let n = pix0.len() - PIXEL_LEN;
+            let r = (i16::from(pix0[R]) + i16::from(pix0[n + R]) + i16::from(pix1[R]) + i16::from(pix1[n + R]) + 2) / 4;
+            let g = (i16::from(pix0[G]) + i16::from(pix0[n + G]) + i16::from(pix1[G]) + i16::from(pix1[n + G]) + 2) / 4;
+            let b = (i16::from(pix0[B]) + i16::from(pix0[n + B]) + i16::from(pix1[B]) + i16::from(pix1[n + B]) + 2) / 4;
 
-            *u = (((-38 * r + 112 * b - 74 * g) >> 8) + 128) as u8;
-            *v = (((112 * r - 18 * b - 94 * g) >> 8) + 128) as u8;
+            *u = (((weights.u[0] * r + weights.u[1] * g + weights.u[2] * b) >> 8) + 128) as u8;
+            *v = (((weights.v[0] * r + weights.v[1] * g + weights.v[2] * b) >> 8) + 128) as u8;
         }
     }
 }
 
+/// Converts pixels of `PIXEL_LEN` bytes each, with channels at byte offsets `R`, `G` and `B`, into 420 Y, U and V
+/// buffers using SIMD.
+///
+/// The width must be a multiple of 8.
+#[allow(clippy::similar_names)]
+fn write_yuv_packed_simd<const PIXEL_LEN: usize, const R: usize, const G: usize, const B: usize>(
+    data: &[u8],
+    row_stride: usize,
+    dimensions: (usize, usize),
+    weights: RgbToYuv,
+    y_buf: &mut [u8],
+    u_buf: &mut [u8],
+    v_buf: &mut [u8],
+) {
+    const STEP: usize = 8;
+    const UV_STEP: usize = STEP / 2;
+
+    let (width, height) = dimensions;
+    assert_eq!(width % STEP, 0);
+
+    // For odd heights the last row is paired with itself.
+    let rows = packed_rows::<PIXEL_LEN>(data, row_stride, dimensions);
+    let r1 = rows.clone().step_by(2);
+    let r2 = rows.clone().skip(1).step_by(2).chain(rows.last().filter(|_| height % 2 == 1));
+
+    // Each chroma row covers two luma rows; for odd heights the last chunk only holds one.
+    let y_rows = y_buf.chunks_mut(2 * width);
+    let u_rows = u_buf.chunks_exact_mut(width / 2);
+    let v_rows = v_buf.chunks_exact_mut(width / 2);
+
+    for ((((r1, r2), y_rows), u_row), v_row) in r1.zip(r2).zip(y_rows).zip(u_rows).zip(v_rows) {
+        let (y_row0, y_row1) = y_rows.split_at_mut(width);
+        let pixels = r1.chunks_exact(STEP * PIXEL_LEN).zip(r2.chunks_exact(STEP * PIXEL_LEN));
+        let chroma = u_row.chunks_exact_mut(UV_STEP).zip(v_row.chunks_exact_mut(UV_STEP));
+
+        for (i, ((pix0, pix1), (u, v))) in pixels.zip(chroma).enumerate() {
+            let (y_pack, uv_pack) = rgb_to_yuv420_simd::<PIXEL_LEN, R, G, B>(pix0, pix1, &weights);
+
+            y_row0[i * STEP..(i + 1) * STEP].copy_from_slice(&y_pack[..STEP]);
+            if let Some(y_row1) = y_row1.get_mut(i * STEP..(i + 1) * STEP) {
+                y_row1.copy_from_slice(&y_pack[STEP..]);
+            }
+            u.copy_from_slice(&uv_pack[..UV_STEP]);
+            v.copy_from_slice(&uv_pack[UV_STEP..STEP]);
+        }
+    }
+}
+
+/// Converts two rows of 8 pixels each into 16 Y values (8 per row), followed by 4 U and 4 V values.
+///
+/// Uses the same integer math as [`write_yuv_packed_scalar`], so results are bit exact. Pixels are loaded as 4 byte
+/// pixels into 16 bit lanes and split into their low and high bytes, and the luma weights are arranged to match the
+/// channel offsets, so they can be applied with a single multiply-add regardless of the layout.
+#[allow(clippy::inline_always)]
+#[allow(clippy::similar_names)]
+#[inline(always)]
+fn rgb_to_yuv420_simd<const PIXEL_LEN: usize, const R: usize, const G: usize, const B: usize>(
+    pix0: &[u8],
+    pix1: &[u8],
+    weights: &RgbToYuv,
+) -> ([u8; 16], [u8; 16]) {
+    // Byte offset within a pixel of the low byte of its first 16 bit lane.
+    const LOW: usize = if cfg!(target_endian = "little") { 0 } else { 1 };
+    // Spreads 4 pixels of 3 bytes to 4 bytes each, the fourth byte is 0.
+    const SPREAD: [u8; 16] = [0, 1, 2, 0x80, 3, 4, 5, 0x80, 6, 7, 8, 0x80, 9, 10, 11, 0x80];
+
+    // Luma weight of the byte at the given offset within a pixel.
+    let weight = |offset: usize| match offset {
+        _ if offset == R => weights.y[0],
+        _ if offset == G => weights.y[1],
+        _ if offset == B => weights.y[2],
+        _ => 0,
+    };
+
+    let low_byte = i16x16::splat(0xFF);
+    let low_mul = i16x16::from(array::from_fn::<i16, 16, _>(|i| weight((i % 2) * 2 + LOW)));
+    let high_mul = i16x16::from(array::from_fn::<i16, 16, _>(|i| weight((i % 2) * 2 + 1 - LOW)));
+
+    // Splits 8 pixels into the low and high bytes of their two 16 bit lanes.
+    let load = |pixels: &[u8]| -> (i16x16, i16x16) {
+        let lanes: i16x16 = if PIXEL_LEN == 4 {
+            cast::<[u8; 32], _>(pixels[..32].try_into().expect("8 pixels"))
+        } else {
+            let spread = u8x16::new(SPREAD);
+            let low = u8x16::new(pixels[..16].try_into().expect("8 pixels"));
+            let high = u8x16::new(pixels[8..24].try_into().expect("8 pixels"));
+            cast([low.shuffle(spread), high.shuffle(spread + u8x16::splat(4))])
+        };
+        (lanes & low_byte, (lanes >> 8) & low_byte)
+    };
+
+    let luma = |low: i16x16, high: i16x16| {
+        let y: i32x8 = ((low.dot(low_mul) + high.dot(high_mul)) >> 8) + i32x8::splat(i32::from(weights.y_offset));
+        i16x8::from_i32x8_truncate(y)
+    };
+
+    let (low0, high0) = load(pix0);
+    let (low1, high1) = load(pix1);
+
+    // Vertical sums first, then horizontal sums of neighboring pixels for the 2x2 averages.
+    let low = cast::<i16x16, i32x8>(low0 + low1);
+    let high = cast::<i16x16, i32x8>(high0 + high1);
+    let average = |offset: usize| {
+        // Each 32 bit lane holds the sums of one pixel.
+        let lanes = if offset % 2 == LOW { low } else { high };
+        let shift = if offset / 2 == LOW { 0 } else { 16 };
+        let sums = i16x8::from_i32x8_saturate((lanes >> shift) & i32x8::splat(0xFFFF));
+        // Lanes 0..4 compute U and lanes 4..8 compute V, both from the same averages.
+        let sums = cast::<[i16x8; 2], i16x16>([sums, sums]).dot(i16x16::splat(1));
+        i16x8::from_i32x8_saturate((sums + i32x8::splat(2)) >> 2)
+    };
+
+    // Lanes 0..4 are weighted for U and lanes 4..8 for V.
+    let chroma_mul = |channel: usize| {
+        i16x8::from(array::from_fn::<i16, 8, _>(|i| {
+            if i < 4 { weights.u[channel] } else { weights.v[channel] }
+        }))
+    };
+    let (r_mul, g_mul, b_mul) = (chroma_mul(0), chroma_mul(1), chroma_mul(2));
+
+    let uv: i16x8 = ((average(R) * r_mul + average(G) * g_mul + average(B) * b_mul) >> 8) + i16x8::splat(128);
+
+    (
+        u8x16::narrow_i16x8(luma(low0, high0), luma(low1, high1)).to_array(),
+        u8x16::narrow_i16x8(uv, uv).to_array(),
+    )
+}
+
 #[cfg(test)]
 mod test {
     use crate::OpenH264API;
     use crate::decoder::{Decoder, DecoderConfig};
-    use crate::formats::rgb2yuv::{write_yuv_by_pixel, write_yuv_scalar};
-    use crate::formats::{RgbSliceU8, YUVSource};
+    use crate::formats::rgb2yuv::{write_yuv_by_pixel, write_yuv_scalar, write_yuv_simd};
+    use crate::formats::{
+        AbgrSliceU8, AbgrSliceU32, ArgbSliceU8, ArgbSliceU32, BgrSliceU8, BgraSliceU8, BgraSliceU32, ColorMatrix, ColorSpace,
+        RGB8Source, RgbSliceU8, RgbaSliceU8, RgbaSliceU32, YUVBuffer, YUVSource,
+    };
     use std::iter::zip;
 
     #[test]
@@ -115,8 +371,22 @@ mod test {
         let mut u_scalar = vec![0_u8; dim.0 * dim.1 / 2];
         let mut v_scalar = vec![0_u8; dim.0 * dim.1 / 2];
 
-        write_yuv_by_pixel(rgb_slice, dim, &mut y_by_pixel, &mut u_by_pixel, &mut v_by_pixel);
-        write_yuv_scalar(rgb_slice, dim, &mut y_scalar, &mut u_scalar, &mut v_scalar);
+        write_yuv_by_pixel(
+            rgb_slice,
+            dim,
+            ColorSpace::default(),
+            &mut y_by_pixel,
+            &mut u_by_pixel,
+            &mut v_by_pixel,
+        );
+        write_yuv_scalar(
+            rgb_slice,
+            dim,
+            ColorSpace::default(),
+            &mut y_scalar,
+            &mut u_scalar,
+            &mut v_scalar,
+        );
 
         let almost_equal = |a: &[u8], b: &[u8]| zip(a, b).map(|(x, y)| u8::abs_diff(*x, *y)).all(|x| x <= 1);
 
@@ -124,4 +394,135 @@ mod test {
         assert!(almost_equal(&u_by_pixel, &u_scalar));
         assert!(almost_equal(&v_by_pixel, &v_scalar));
     }
+
+    /// Converts `source` with both the scalar and SIMD kernels, and makes sure they agree.
+    fn convert(source: impl RGB8Source + Copy) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        convert_with_color_space(source, ColorSpace::default())
+    }
+
+    fn convert_with_color_space(source: impl RGB8Source + Copy, color_space: ColorSpace) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let (w, h) = source.dimensions();
+        let planes = || {
+            (
+                vec![0_u8; w * h],
+                vec![0_u8; w / 2 * h.div_ceil(2)],
+                vec![0_u8; w / 2 * h.div_ceil(2)],
+            )
+        };
+        let (mut scalar, mut simd) = (planes(), planes());
+
+        write_yuv_scalar(source, (w, h), color_space, &mut scalar.0, &mut scalar.1, &mut scalar.2);
+        write_yuv_simd(source, (w, h), color_space, &mut simd.0, &mut simd.1, &mut simd.2);
+        assert_eq!(scalar, simd, "{:?} {color_space:?}", source.layout());
+
+        scalar
+    }
+
+    #[test]
+    fn write_yuv_simd_matches_scalar_for_all_layouts() {
+        for (w, h) in [(8, 2), (16, 3), (24, 16), (64, 33)] {
+            let rgb = (0..w * h * 3).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+            let u8s = |f: fn(&[u8]) -> [u8; 4]| rgb.chunks_exact(3).flat_map(f).collect::<Vec<_>>();
+            let u32s = |f: fn(&[u8]) -> [u8; 4]| rgb.chunks_exact(3).map(|px| u32::from_be_bytes(f(px))).collect::<Vec<_>>();
+
+            let bgr = rgb.chunks_exact(3).flat_map(|px| [px[2], px[1], px[0]]).collect::<Vec<_>>();
+            let rgba = u8s(|px| [px[0], px[1], px[2], 255]);
+            let bgra = u8s(|px| [px[2], px[1], px[0], 255]);
+            let argb = u8s(|px| [255, px[0], px[1], px[2]]);
+            let abgr = u8s(|px| [255, px[2], px[1], px[0]]);
+            let rgba32 = u32s(|px| [px[0], px[1], px[2], 255]);
+            let bgra32 = u32s(|px| [px[2], px[1], px[0], 255]);
+            let argb32 = u32s(|px| [255, px[0], px[1], px[2]]);
+            let abgr32 = u32s(|px| [255, px[2], px[1], px[0]]);
+
+            let expected = convert(RgbSliceU8::new(&rgb, (w, h)));
+
+            assert_eq!(convert(BgrSliceU8::new(&bgr, (w, h))), expected);
+            assert_eq!(convert(RgbaSliceU8::new(&rgba, (w, h))), expected);
+            assert_eq!(convert(BgraSliceU8::new(&bgra, (w, h))), expected);
+            assert_eq!(convert(ArgbSliceU8::new(&argb, (w, h))), expected);
+            assert_eq!(convert(AbgrSliceU8::new(&abgr, (w, h))), expected);
+            assert_eq!(convert(RgbaSliceU32::new(&rgba32, (w, h))), expected);
+            assert_eq!(convert(BgraSliceU32::new(&bgra32, (w, h))), expected);
+            assert_eq!(convert(ArgbSliceU32::new(&argb32, (w, h))), expected);
+            assert_eq!(convert(AbgrSliceU32::new(&abgr32, (w, h))), expected);
+        }
+    }
+
+    #[test]
+    fn write_yuv_honors_row_stride() {
+        for (w, h) in [(8_usize, 2_usize), (13, 5), (64, 33)] {
+            let rgb = (0..w * h * 3).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+            let pixels = rgb
+                .chunks_exact(3)
+                .map(|px| u32::from_be_bytes([px[0], px[1], px[2], 255]))
+                .collect::<Vec<_>>();
+
+            // Rows aligned to 256 bytes, with an unpadded last row.
+            let stride = (w * 3).next_multiple_of(256);
+            let mut rgb_padded = vec![0xAA; (h - 1) * stride + w * 3];
+            for (row, padded) in rgb.chunks_exact(w * 3).zip(rgb_padded.chunks_mut(stride)) {
+                padded[..w * 3].copy_from_slice(row);
+            }
+
+            let stride32 = (w * 4).next_multiple_of(256);
+            let mut pixels_padded = vec![0xAAAA_AAAA; h * stride32 / 4];
+            for (row, padded) in pixels.chunks_exact(w).zip(pixels_padded.chunks_mut(stride32 / 4)) {
+                padded[..w].copy_from_slice(row);
+            }
+
+            let planes = |yuv: YUVBuffer| (yuv.y().to_vec(), yuv.u().to_vec(), yuv.v().to_vec());
+            let expected = planes(YUVBuffer::from_rgb8_source(RgbSliceU8::new(&rgb, (w, h))));
+            let rgb_padded = RgbSliceU8::with_stride(&rgb_padded, (w, h), stride);
+            let pixels_padded = RgbaSliceU32::with_stride(&pixels_padded, (w, h), stride32);
+
+            assert_eq!(planes(YUVBuffer::from_rgb8_source(rgb_padded)), expected);
+            assert_eq!(planes(YUVBuffer::from_rgb8_source(pixels_padded)), expected);
+            assert_eq!(
+                planes(YUVBuffer::from_rgb_source(rgb_padded)),
+                planes(YUVBuffer::from_rgb_source(RgbSliceU8::new(&rgb, (w, h))))
+            );
+        }
+    }
+
+    #[test]
+    fn write_yuv_color_spaces() {
+        let dims = (16, 4);
+        let rgb = (0..dims.0 * dims.1 * 3).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+        let rgba = rgb
+            .chunks_exact(3)
+            .flat_map(|px| [px[0], px[1], px[2], 255])
+            .collect::<Vec<_>>();
+        let white = vec![255_u8; dims.0 * dims.1 * 3];
+        let black = vec![0_u8; dims.0 * dims.1 * 3];
+
+        for matrix in [ColorMatrix::Bt601, ColorMatrix::Bt709, ColorMatrix::Bt2020] {
+            for full_range in [false, true] {
+                let color_space = ColorSpace::new(matrix, full_range);
+                let (y_max, y_min) = if full_range { (255, 0) } else { (235, 16) };
+
+                let expected = convert_with_color_space(RgbSliceU8::new(&rgb, dims), color_space);
+                assert_eq!(convert_with_color_space(RgbaSliceU8::new(&rgba, dims), color_space), expected);
+
+                let (y, u, v) = convert_with_color_space(RgbSliceU8::new(&white, dims), color_space);
+                assert!(y.iter().all(|&y| y == y_max), "{color_space:?}");
+                assert!(u.iter().chain(&v).all(|&c| c == 128), "{color_space:?}");
+
+                let (y, u, v) = convert_with_color_space(RgbSliceU8::new(&black, dims), color_space);
+                assert!(y.iter().all(|&y| y == y_min), "{color_space:?}");
+                assert!(u.iter().chain(&v).all(|&c| c == 128), "{color_space:?}");
+            }
+        }
+
+        // Pure red has a much lower luma in BT.709 than in BT.601.
+        let red = [255_u8, 0, 0].repeat(dims.0 * dims.1);
+        let luma = |color_space| {
+            let mut yuv = YUVBuffer::new(dims.0, dims.1);
+            yuv.read_rgb8_with_color_space(RgbSliceU8::new(&red, dims), color_space);
+            yuv.y()[0]
+        };
+        assert_eq!(luma(ColorSpace::bt601()), 81);
+        assert_eq!(luma(ColorSpace::bt709()), 62);
+        assert_eq!(luma(ColorSpace::bt709().full_range(true)), 53);
+    }
 }
@@ -1,6 +1,8 @@
-use crate::formats::RGBSource;
+use crate::formats::nv12::{NV12Slices, write_yuv_from_nv12};
 use crate::formats::rgb::RGB8Source;
-use crate::formats::rgb2yuv::{write_yuv_by_pixel, write_yuv_scalar};
+use crate::formats::rgb2yuv::{write_yuv_by_pixel, write_yuv_scalar, write_yuv_simd};
+use crate::formats::yuv2rgb::{write_rgb8_layout, write_rgb565};
+use crate::formats::{ColorSpace, RGB8Layout, RGBSource};
 
 /// Allows the [Encoder](crate::encoder::Encoder) to be generic over a YUV source.
 pub trait YUVSource {
@@ -59,6 +61,103 @@ pub trait YUVSource {
         let (w, h) = self.dimensions();
         w * h * 4
     }
+
+    /// Writes the image into a byte buffer of size `w * h * layout.bytes_per_pixel()` as packed 8 bit pixels in
+    /// the given layout, e.g., [`RGB8Layout::Bgra`] for most window surfaces.
+    ///
+    /// Alpha channels are set to 255, so they are opaque whether or not your surface expects premultiplied alpha.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target image dimension don't match the size above.
+    fn write_rgb8_layout(&self, target: &mut [u8], layout: RGB8Layout, color_space: ColorSpace) {
+        let (w, h) = self.dimensions();
+        let wanted = w * h * layout.bytes_per_pixel();
+
+        assert_eq!(
+            target.len(),
+            wanted,
+            "Target {layout:?} array does not match image dimensions. Wanted: {w} * {h} * {} = {wanted}, got {}",
+            layout.bytes_per_pixel(),
+            target.len()
+        );
+
+        write_rgb8_layout(
+            self.y(),
+            self.u(),
+            self.v(),
+            (w, h),
+            self.strides(),
+            layout,
+            color_space,
+            target,
+        );
+    }
+
+    /// Writes the image into a buffer of `w * h` RGB565 pixels, with red in the high 5 bits, green in the middle
+    /// 6 bits and blue in the low 5 bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target image dimension don't match the size above.
+    fn write_rgb565(&self, target: &mut [u16], color_space: ColorSpace) {
+        let (w, h) = self.dimensions();
+
+        assert_eq!(
+            target.len(),
+            w * h,
+            "Target RGB565 array does not match image dimensions. Wanted: {w} * {h} = {}, got {}",
+            w * h,
+            target.len()
+        );
+
+        write_rgb565(self.y(), self.u(), self.v(), (w, h), self.strides(), color_space, target);
+    }
+}
+
+/// Frames that can be passed to the [Encoder](crate::encoder::Encoder).
+///
+/// This is implemented for every [`YUVSource`], which is encoded as-is, and for formats OpenH264 can't encode
+/// natively, such as [`NV12Slices`] or [packed 4:2:2](crate::formats::YuyvSliceU8) data, which are converted first.
+pub trait EncoderInput {
+    /// Returns this frame as YUV 4:2:0 data of even width and height, converting it into `buffer` if needed.
+    ///
+    /// Since H.264 can only crop 4:2:0 frames to even sizes, frames of odd size are padded by repeating the last
+    /// column or row. The encoder keeps `buffer` between calls, so conversions can reuse it if its dimensions match.
+    fn as_yuv<'a>(&'a self, buffer: &'a mut Option<YUVBuffer>) -> &'a dyn YUVSource;
+}
+
+impl<T: YUVSource> EncoderInput for T {
+    fn as_yuv<'a>(&'a self, buffer: &'a mut Option<YUVBuffer>) -> &'a dyn YUVSource {
+        let (width, height) = self.dimensions();
+
+        if width % 2 == 0 && height % 2 == 0 {
+            return self;
+        }
+
+        YUVBuffer::write_even(buffer, (width, height), |y_buf, y_stride, u_buf, v_buf| {
+            let strides = self.strides();
+            let chroma_width = width.div_ceil(2);
+
+            for (row, source) in y_buf.chunks_exact_mut(y_stride).zip(self.y().chunks(strides.0)).take(height) {
+                row[..width].copy_from_slice(&source[..width]);
+            }
+
+            for (buf, plane, stride) in [(u_buf, self.u(), strides.1), (v_buf, self.v(), strides.2)] {
+                for (row, source) in buf.chunks_exact_mut(chroma_width).zip(plane.chunks(stride)) {
+                    row.copy_from_slice(&source[..chroma_width]);
+                }
+            }
+        })
+    }
+}
+
+impl EncoderInput for NV12Slices<'_> {
+    fn as_yuv<'a>(&'a self, buffer: &'a mut Option<YUVBuffer>) -> &'a dyn YUVSource {
+        YUVBuffer::write_even(buffer, self.dimensions(), |y_buf, y_stride, u_buf, v_buf| {
+            write_yuv_from_nv12(self, y_buf, y_stride, u_buf, v_buf);
+        })
+    }
 }
 
 /// Converts RGB to YUV data.
@@ -72,42 +171,97 @@ pub struct YUVBuffer {
 impl YUVBuffer {
     /// Creates a new YUV buffer from the given vec.
     ///
-    /// The vec's length should be `3 * (width * height) / 2`.
+    /// The vec's length should be `width * height + 2 * (width.div_ceil(2) * height.div_ceil(2))`, which is
+    /// `3 * (width * height) / 2` for even sizes.
     ///
     /// # Panics
     ///
-    /// May panic if the given sizes are not multiples of 2, or the yuv buffer's size mismatches.
+    /// May panic if the yuv buffer's size mismatches.
     pub fn from_vec(yuv: Vec<u8>, width: usize, height: usize) -> Self {
-        assert_eq!(width % 2, 0, "width needs to be a multiple of 2");
-        assert_eq!(height % 2, 0, "height needs to be a multiple of 2");
-        assert_eq!(yuv.len(), (3 * (width * height)) / 2, "YUV buffer needs to be properly sized");
+        assert_eq!(yuv.len(), Self::len(width, height), "YUV buffer needs to be properly sized");
 
         Self { yuv, width, height }
     }
 
     /// Allocates a new YUV buffer with the given width and height.
     ///
-    /// Both dimensions must be even.
-    ///
-    /// # Panics
-    ///
-    /// May panic if the given sizes are not multiples of 2.
+    /// Odd dimensions are supported, in which case the U and V planes are rounded up.
     pub fn new(width: usize, height: usize) -> Self {
-        assert_eq!(width % 2, 0, "width needs to be a multiple of 2");
-        assert_eq!(height % 2, 0, "height needs to be a multiple of 2");
-
         Self {
-            yuv: vec![0u8; (3 * (width * height)) / 2],
+            yuv: vec![0u8; Self::len(width, height)],
             width,
             height,
         }
     }
 
-    /// Allocates a new YUV buffer with the given width and height and data.
-    ///
-    /// # Panics
+    /// Allocates a new YUV buffer from NV12 or NV21 data.
+    pub fn from_nv12_source(nv12: &NV12Slices) -> Self {
+        let mut rval = Self::new(nv12.dimensions().0, nv12.dimensions().1);
+        rval.read_nv12(nv12);
+        rval
+    }
+
+    /// Returns the buffer stored in `buffer`, replacing it if it doesn't match the given dimensions.
+    pub(crate) fn reuse(buffer: &mut Option<Self>, dimensions: (usize, usize)) -> &mut Self {
+        if buffer.as_ref().is_some_and(|existing| existing.dimensions() != dimensions) {
+            *buffer = None;
+        }
+
+        buffer.get_or_insert_with(|| Self::new(dimensions.0, dimensions.1))
+    }
+
+    /// Writes a frame of the given dimensions into the buffer stored in `buffer`, padded to even dimensions.
     ///
-    /// May panic if invoked with an RGB source where the dimensions are not multiples of 2.
+    /// `f` receives the Y plane with its row stride, and the U and V planes, which are the same for both sizes. It
+    /// only has to write the frame itself, the last column and row are repeated afterwards.
+    pub(crate) fn write_even(
+        buffer: &mut Option<Self>,
+        dimensions: (usize, usize),
+        f: impl FnOnce(&mut [u8], usize, &mut [u8], &mut [u8]),
+    ) -> &mut Self {
+        let (width, height) = dimensions;
+        let rval = Self::reuse(buffer, (width.next_multiple_of(2), height.next_multiple_of(2)));
+        let stride = rval.width;
+
+        rval.write_planes(|y_buf, u_buf, v_buf| f(y_buf, stride, u_buf, v_buf));
+
+        let y_buf = &mut rval.yuv[..stride * rval.height];
+
+        if width < stride {
+            for row in y_buf.chunks_exact_mut(stride) {
+                row[width] = row[width - 1];
+            }
+        }
+
+        if height < rval.height {
+            let (rows, last_row) = y_buf.split_at_mut(stride * height);
+            last_row.copy_from_slice(&rows[rows.len() - stride..]);
+        }
+
+        rval
+    }
+
+    /// Gives mutable access to the Y, U and V planes.
+    #[allow(clippy::similar_names)]
+    pub(crate) fn write_planes(&mut self, f: impl FnOnce(&mut [u8], &mut [u8], &mut [u8])) {
+        let u_base = self.width * self.height;
+        let v_base = self.dimensions_uv().0 * self.dimensions_uv().1;
+        let (y_buf, uv_buf) = self.yuv.split_at_mut(u_base);
+        let (u_buf, v_buf) = uv_buf.split_at_mut(v_base);
+        f(y_buf, u_buf, v_buf);
+    }
+
+    /// Size of the U and V planes, rounded up for odd dimensions.
+    const fn dimensions_uv(&self) -> (usize, usize) {
+        (self.width.div_ceil(2), self.height.div_ceil(2))
+    }
+
+    /// Total length of a buffer of the given size.
+    const fn len(width: usize, height: usize) -> usize {
+        width * height + 2 * (width.div_ceil(2) * height.div_ceil(2))
+    }
+
+    /// Allocates a new YUV buffer with the given width and height and data.
     pub fn from_rgb_source(rgb: impl RGBSource) -> Self {
         let mut rval = Self::new(rgb.dimensions().0, rgb.dimensions().1);
         rval.read_rgb(rgb);
@@ -118,46 +272,75 @@ impl YUVBuffer {
     ///
     /// This is the faster version of [`Self::from_rgb_source`] and you should generally
     /// use this one.
-    ///
-    /// # Panics
-    ///
-    /// May panic if invoked with an RGB source where the dimensions are not multiples of 2.
     pub fn from_rgb8_source(rgb: impl RGB8Source) -> Self {
         let mut rval = Self::new(rgb.dimensions().0, rgb.dimensions().1);
         rval.read_rgb8(rgb);
         rval
     }
 
-    /// Reads an RGB buffer, converts it to YUV and stores it.
+    /// Reads an RGB buffer, converts it to BT.601 limited range YUV and stores it.
     ///
     /// # Panics
     ///
     /// May panic if the given `rgb` does not match the internal format.
-    #[allow(clippy::similar_names)]
     pub fn read_rgb(&mut self, rgb: impl RGBSource) {
+        self.read_rgb_with_color_space(rgb, ColorSpace::default());
+    }
+
+    /// Reads an RGB buffer, converts it to YUV of the given color space and stores it.
+    ///
+    /// # Panics
+    ///
+    /// May panic if the given `rgb` does not match the internal format.
+    #[allow(clippy::similar_names)]
+    pub fn read_rgb_with_color_space(&mut self, rgb: impl RGBSource, color_space: ColorSpace) {
         let dimensions = self.dimensions();
-        let u_base = self.width * self.height;
-        let v_base = u_base / 4;
-        let (y_buf, uv_buf) = self.yuv.split_at_mut(u_base);
-        let (u_buf, v_buf) = uv_buf.split_at_mut(v_base);
-        write_yuv_by_pixel(rgb, dimensions, y_buf, u_buf, v_buf);
+        self.write_planes(|y_buf, u_buf, v_buf| write_yuv_by_pixel(rgb, dimensions, color_space, y_buf, u_buf, v_buf));
     }
 
-    /// Reads an RGB8 buffer, converts it to YUV and stores it.
+    /// Reads an RGB8 buffer, converts it to BT.601 limited range YUV and stores it.
     ///
     /// This is the faster version of [`Self::read_rgb`] and you should generally use this one.
     ///
     /// # Panics
     ///
     /// May panic if the given `rgb` does not match the internal format.
-    #[allow(clippy::similar_names)]
     pub fn read_rgb8(&mut self, rgb: impl RGB8Source) {
+        self.read_rgb8_with_color_space(rgb, ColorSpace::default());
+    }
+
+    /// Reads an RGB8 buffer, converts it to YUV of the given color space and stores it.
+    ///
+    /// Use this with the color space you signal in the bitstream, e.g., [`ColorSpace::bt709()`] together
+    /// with [`VuiConfig::bt709()`](crate::encoder::VuiConfig::bt709).
+    ///
+    /// # Panics
+    ///
+    /// May panic if the given `rgb` does not match the internal format.
+    #[allow(clippy::similar_names)]
+    pub fn read_rgb8_with_color_space(&mut self, rgb: impl RGB8Source, color_space: ColorSpace) {
         let dimensions = self.dimensions();
-        let u_base = self.width * self.height;
-        let v_base = u_base / 4;
-        let (y_buf, uv_buf) = self.yuv.split_at_mut(u_base);
-        let (u_buf, v_buf) = uv_buf.split_at_mut(v_base);
-        write_yuv_scalar(rgb, dimensions, y_buf, u_buf, v_buf);
+
+        // for SIMD math, image needs to:
+        //   - have a width divisible by 8
+        //   - have at least two rows
+        if dimensions.0 % 8 == 0 && dimensions.1 >= 2 {
+            self.write_planes(|y_buf, u_buf, v_buf| write_yuv_simd(rgb, dimensions, color_space, y_buf, u_buf, v_buf));
+        } else {
+            self.write_planes(|y_buf, u_buf, v_buf| write_yuv_scalar(rgb, dimensions, color_space, y_buf, u_buf, v_buf));
+        }
+    }
+
+    /// Reads NV12 or NV21 data, splits its chroma planes and stores it.
+    ///
+    /// # Panics
+    ///
+    /// May panic if the given `nv12` does not match the internal format.
+    #[allow(clippy::similar_names)]
+    pub fn read_nv12(&mut self, nv12: &NV12Slices) {
+        assert_eq!(nv12.dimensions(), self.dimensions());
+
+        self.write_planes(|y_buf, u_buf, v_buf| write_yuv_from_nv12(nv12, y_buf, nv12.dimensions().0, u_buf, v_buf));
     }
 }
 
@@ -167,7 +350,8 @@ impl YUVSource for YUVBuffer {
     }
 
     fn strides(&self) -> (usize, usize, usize) {
-        (self.width, self.width / 2, self.width / 2)
+        let (chroma_width, _) = self.dimensions_uv();
+        (self.width, chroma_width, chroma_width)
     }
 
     fn y(&self) -> &[u8] {
@@ -176,13 +360,14 @@ impl YUVSource for YUVBuffer {
 
     fn u(&self) -> &[u8] {
         let base_u = self.width * self.height;
-        &self.yuv[base_u..base_u + base_u / 4]
+        let (chroma_width, chroma_height) = self.dimensions_uv();
+        &self.yuv[base_u..base_u + chroma_width * chroma_height]
     }
 
     fn v(&self) -> &[u8] {
         let base_u = self.width * self.height;
-        let base_v = base_u + base_u / 4;
-        &self.yuv[base_v..]
+        let (chroma_width, chroma_height) = self.dimensions_uv();
+        &self.yuv[base_u + chroma_width * chroma_height..]
     }
 }
 
@@ -200,20 +385,20 @@ impl<'a> YUVSlices<'a> {
     ///
     /// Assume you have some dimension `(w, h)` that is your actual image size. In addition,
     /// you will have strides `(sy, su, sv)` that specify how many pixels / bytes per row
-    /// are actually used be used. Strides must be larger or equal than `w` (y) or `w / 2` (uv)
-    /// respectively.
+    /// are actually used be used. Strides must be larger or equal than `w` (y) or `w.div_ceil(2)` (uv)
+    /// respectively, and the U and V planes have `h.div_ceil(2)` rows.
     ///
     /// # Panics
     ///
     /// This will panic if the given slices, strides or dimensions don't match.
     pub fn new(yuv: (&'a [u8], &'a [u8], &'a [u8]), dimensions: (usize, usize), strides: (usize, usize, usize)) -> Self {
         assert!(strides.0 >= dimensions.0);
-        assert!(strides.1 >= dimensions.0 / 2);
-        assert!(strides.2 >= dimensions.0 / 2);
+        assert!(strides.1 >= dimensions.0.div_ceil(2));
+        assert!(strides.2 >= dimensions.0.div_ceil(2));
 
         assert_eq!(dimensions.1 * strides.0, yuv.0.len());
-        assert_eq!((dimensions.1 / 2) * strides.1, yuv.1.len());
-        assert_eq!((dimensions.1 / 2) * strides.2, yuv.2.len());
+        assert_eq!(dimensions.1.div_ceil(2) * strides.1, yuv.1.len());
+        assert_eq!(dimensions.1.div_ceil(2) * strides.2, yuv.2.len());
 
         Self {
             dimensions,
@@ -247,9 +432,9 @@ impl YUVSource for YUVSlices<'_> {
 
 #[cfg(test)]
 mod tests {
-    use super::{YUVBuffer, YUVSlices};
+    use super::{EncoderInput, YUVBuffer, YUVSlices};
     use crate::formats::yuv2rgb::{write_rgb8_f32x8, write_rgb8_scalar};
-    use crate::formats::{RgbSliceU8, YUVSource};
+    use crate::formats::{ColorSpace, RGB8Layout, RgbSliceU8, YUVSource};
     use rand::prelude::IteratorRandom;
     use rand::rngs::ThreadRng;
 
@@ -298,6 +483,37 @@ mod tests {
         assert_eq!(yuv.strides_i32().2, 2);
     }
 
+    #[test]
+    fn rgb_to_yuv_conversion_odd_3x3() {
+        let mut data = [255u8; 27];
+        data[24..].copy_from_slice(&[0, 0, 0]);
+
+        for yuv in [
+            YUVBuffer::from_rgb_source(RgbSliceU8::new(&data, (3, 3))),
+            YUVBuffer::from_rgb8_source(RgbSliceU8::new(&data, (3, 3))),
+        ] {
+            assert_eq!(yuv.y(), [235u8, 235u8, 235u8, 235u8, 235u8, 235u8, 235u8, 235u8, 16u8]);
+            assert_eq!(yuv.u(), [128u8, 128u8, 128u8, 128u8]);
+            assert_eq!(yuv.v(), [128u8, 128u8, 128u8, 128u8]);
+            assert_eq!(yuv.strides(), (3, 2, 2));
+        }
+    }
+
+    #[test]
+    fn yuv_source_padded_to_even() {
+        let y = [1u8, 2, 3, 4, 5, 6, 7, 8, 9];
+        let u = [10u8, 11, 12, 13];
+        let v = [20u8, 21, 22, 23];
+        let source = YUVSlices::new((&y, &u, &v), (3, 3), (3, 2, 2));
+        let mut buffer = None;
+        let yuv = source.as_yuv(&mut buffer);
+
+        assert_eq!(yuv.dimensions(), (4, 4));
+        assert_eq!(yuv.y(), [1u8, 2, 3, 3, 4, 5, 6, 6, 7, 8, 9, 9, 7, 8, 9, 9]);
+        assert_eq!(yuv.u(), u);
+        assert_eq!(yuv.v(), v);
+    }
+
     #[test]
     #[should_panic = "strides.0 >= dimensions.0"]
     fn test_new_stride_less_than_width() {
@@ -308,7 +524,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic = "strides.1 >= dimensions.0 / 2"]
+    #[should_panic = "strides.1 >= dimensions.0.div_ceil(2)"]
     fn test_new_u_stride_less_than_half_width() {
         let y = vec![0u8; 20];
         let u = vec![0u8; 5];
@@ -317,7 +533,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic = "strides.2 >= dimensions.0 / 2"]
+    #[should_panic = "strides.2 >= dimensions.0.div_ceil(2)"]
     fn test_new_v_stride_less_than_half_width() {
         let y = vec![0u8; 20];
         let u = vec![0u8; 5];
@@ -360,6 +576,26 @@ mod tests {
         let _ = YUVSlices::new((&y, &u, &v), (10, 2), (10, 5, 5));
     }
 
+    #[test]
+    fn write_rgb8_layout_from_padded_slices() {
+        // 3x3 image, white except for a black bottom right pixel, with padded rows.
+        let y = [235u8, 235, 235, 0, 235, 235, 235, 0, 235, 235, 16, 0];
+        let u = [128u8, 128, 0, 128, 128, 0];
+        let v = [128u8, 128, 0, 128, 128, 0];
+        let source = YUVSlices::new((&y, &u, &v), (3, 3), (4, 3, 3));
+
+        let mut bgra = vec![0; 3 * 3 * 4];
+        source.write_rgb8_layout(&mut bgra, RGB8Layout::Bgra, ColorSpace::default());
+        assert_eq!(bgra[..32], [255; 32]);
+        assert_eq!(bgra[32..], [0, 0, 0, 255]);
+
+        // Padding repeats the last row and column.
+        let mut rgb565 = vec![0; 4 * 4];
+        source.as_yuv(&mut None).write_rgb565(&mut rgb565, ColorSpace::default());
+        assert_eq!(rgb565[..10], [0xFFFF; 10]);
+        assert_eq!(rgb565[10..], [0, 0, 0xFFFF, 0xFFFF, 0, 0]);
+    }
+
     /// Test every YUV value and see, if the SIMD version delivers a similar RGB value.
     #[test]
     fn test_write_rgb8_f32x8_spectrum() {
@@ -374,10 +610,10 @@ mod tests {
                 for v in (0..=255u8).sample(&mut rng, 10) {
                     let (y_plane, u_plane, v_plane) = (vec![y; 16], vec![u; 4], vec![v; 4]);
                     let mut target = vec![0; dim.0 * dim.1 * 3];
-                    write_rgb8_scalar(&y_plane, &u_plane, &v_plane, dim, strides, &mut target);
+                    write_rgb8_scalar(&y_plane, &u_plane, &v_plane, dim, strides, ColorSpace::default(), &mut target);
 
                     let mut target2 = vec![0; dim.0 * dim.1 * 3];
-                    write_rgb8_f32x8(&y_plane, &u_plane, &v_plane, dim, strides, &mut target2);
+                    write_rgb8_f32x8(&y_plane, &u_plane, &v_plane, dim, strides, ColorSpace::default(), &mut target2);
 
                     // compare first pixel
                     for i in 0..3 {
@@ -1,3 +1,5 @@
+use crate::formats::{ColorMatrix, ColorSpace, RGB8Layout};
+
 const Y_MUL: f32 = 255.0 / 219.0;
 const RV_MUL: f32 = 255.0 / 224.0 * 1.402;
 const GV_MUL: f32 = -255.0 / 224.0 * 1.402 * 0.299 / 0.687;
@@ -7,91 +9,342 @@ const BU_MUL: f32 = 255.0 / 224.0 * 1.772;
 const RGB_PIXEL_LEN: usize = 3;
 const RGBA_PIXEL_LEN: usize = 4;
 
-/// Write RGB8 data from YUV420 using scalar (non SIMD) math.
-#[allow(dead_code)]
-pub fn write_rgb8_scalar(
+/// Multipliers converting YUV of a given [`ColorSpace`] into RGB.
+#[derive(Copy, Clone, Debug)]
+struct YuvToRgb {
+    y_offset: f32,
+    y_mul: f32,
+    rv_mul: f32,
+    gv_mul: f32,
+    gu_mul: f32,
+    bu_mul: f32,
+}
+
+impl YuvToRgb {
+    // https://en.wikipedia.org/wiki/YCbCr#ITU-R_BT.601_conversion
+    const fn new(color_space: ColorSpace) -> Self {
+        let (kr, kb) = color_space.color_matrix().kr_kb();
+        let kg = 1.0 - kr - kb;
+
+        match (color_space.color_matrix(), color_space.is_full_range()) {
+            // Keep the constants above for the default, so its output stays the same.
+            (ColorMatrix::Bt601, false) => Self {
+                y_offset: 16.0,
+                y_mul: Y_MUL,
+                rv_mul: RV_MUL,
+                gv_mul: GV_MUL,
+                gu_mul: GU_MUL,
+                bu_mul: BU_MUL,
+            },
+            (_, full_range) => {
+                let (y_offset, y_mul, uv_mul) = if full_range {
+                    (0.0, 1.0, 1.0)
+                } else {
+                    (16.0, Y_MUL, 255.0 / 224.0)
+                };
+                Self {
+                    y_offset,
+                    y_mul,
+                    rv_mul: uv_mul * 2.0 * (1.0 - kr),
+                    gv_mul: -uv_mul * 2.0 * (1.0 - kr) * kr / kg,
+                    gu_mul: -uv_mul * 2.0 * (1.0 - kb) * kb / kg,
+                    bu_mul: uv_mul * 2.0 * (1.0 - kb),
+                }
+            }
+        }
+    }
+
+    /// Converts a single pixel, saturating at the bounds of `u8`.
+    fn rgb(&self, y: u8, u: u8, v: u8) -> [u8; 3] {
+        let y_mul = self.y_mul * (f32::from(y) - self.y_offset);
+        let u = f32::from(u) - 128.0;
+        let v = f32::from(v) - 128.0;
+
+        [
+            self.rv_mul.mul_add(v, y_mul) as u8,
+            self.gv_mul.mul_add(v, self.gu_mul.mul_add(u, y_mul)) as u8,
+            self.bu_mul.mul_add(u, y_mul) as u8,
+        ]
+    }
+
+    /// Converts 8 pixels sharing 4 chroma samples using f32x8 SIMD, saturating at the bounds of `u8`.
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    fn rgb_f32x8(&self, y: [u8; 8], u: [u8; 4], v: [u8; 4]) -> [[u8; 3]; 8] {
+        let upper_bound = wide::f32x8::splat(255.0);
+        let lower_bound = wide::f32x8::splat(0.0);
+
+        let (y_pack, u_pack, v_pack) = pack_into_yuv420_f32x8(&y, &u, &v, self.y_offset);
+        let y_mul = y_pack * wide::f32x8::splat(self.y_mul);
+
+        let r_pack = v_pack.mul_add(wide::f32x8::splat(self.rv_mul), y_mul);
+        let g_pack = v_pack.mul_add(
+            wide::f32x8::splat(self.gv_mul),
+            u_pack.mul_add(wide::f32x8::splat(self.gu_mul), y_mul),
+        );
+        let b_pack = u_pack.mul_add(wide::f32x8::splat(self.bu_mul), y_mul);
+
+        let (r_pack, g_pack, b_pack) = (
+            r_pack.fast_min(upper_bound).fast_max(lower_bound).fast_trunc_int(),
+            g_pack.fast_min(upper_bound).fast_max(lower_bound).fast_trunc_int(),
+            b_pack.fast_min(upper_bound).fast_max(lower_bound).fast_trunc_int(),
+        );
+        let (r_pack, g_pack, b_pack) = (r_pack.as_array(), g_pack.as_array(), b_pack.as_array());
+
+        std::array::from_fn(|i| [r_pack[i] as u8, g_pack[i] as u8, b_pack[i] as u8])
+    }
+}
+
+/// Write RGB8 data from YUV420, using f32x8 SIMD where possible.
+pub fn write_rgb8(
     y_plane: &[u8],
     u_plane: &[u8],
     v_plane: &[u8],
     dim: (usize, usize),
     strides: (usize, usize, usize),
+    color_space: ColorSpace,
     target: &mut [u8],
 ) {
-    for y in 0..dim.1 {
-        for x in 0..dim.0 {
-            let base_tgt = (y * dim.0 + x) * RGB_PIXEL_LEN;
-            let base_y = y * strides.0 + x;
-            let base_u = (y / 2 * strides.1) + (x / 2);
-            let base_v = (y / 2 * strides.2) + (x / 2);
+    // for f32x8 math, image needs to:
+    //   - have a width divisible by 8
+    //   - have at least two rows
+    if dim.0 % 8 == 0 && dim.1 >= 2 {
+        write_rgb8_f32x8(y_plane, u_plane, v_plane, dim, strides, color_space, target);
+    } else {
+        write_rgb8_scalar(y_plane, u_plane, v_plane, dim, strides, color_space, target);
+    }
+}
 
-            let rgb_pixel = &mut target[base_tgt..base_tgt + RGB_PIXEL_LEN];
+/// Write RGBA8 data from YUV420, using f32x8 SIMD where possible.
+pub fn write_rgba8(
+    y_plane: &[u8],
+    u_plane: &[u8],
+    v_plane: &[u8],
+    dim: (usize, usize),
+    strides: (usize, usize, usize),
+    color_space: ColorSpace,
+    target: &mut [u8],
+) {
+    // for f32x8 math, image needs to:
+    //   - have a width divisible by 8
+    //   - have at least two rows
+    if dim.0 % 8 == 0 && dim.1 >= 2 {
+        write_rgba8_f32x8(y_plane, u_plane, v_plane, dim, strides, color_space, target);
+    } else {
+        write_rgba8_scalar(y_plane, u_plane, v_plane, dim, strides, color_space, target);
+    }
+}
+
+/// Write RGB8 data from YUV420 like [`write_rgb8`], spread across up to `num_threads` threads.
+#[allow(clippy::too_many_arguments)]
+pub fn write_rgb8_par(
+    y_plane: &[u8],
+    u_plane: &[u8],
+    v_plane: &[u8],
+    dim: (usize, usize),
+    strides: (usize, usize, usize),
+    color_space: ColorSpace,
+    num_threads: usize,
+    target: &mut [u8],
+) {
+    let planes = (y_plane, u_plane, v_plane);
+    write_bands_par(
+        planes,
+        dim,
+        strides,
+        num_threads,
+        RGB_PIXEL_LEN,
+        target,
+        |(y, u, v), dim, target| {
+            write_rgb8(y, u, v, dim, strides, color_space, target);
+        },
+    );
+}
+
+/// Write RGBA8 data from YUV420 like [`write_rgba8`], spread across up to `num_threads` threads.
+#[allow(clippy::too_many_arguments)]
+pub fn write_rgba8_par(
+    y_plane: &[u8],
+    u_plane: &[u8],
+    v_plane: &[u8],
+    dim: (usize, usize),
+    strides: (usize, usize, usize),
+    color_space: ColorSpace,
+    num_threads: usize,
+    target: &mut [u8],
+) {
+    let planes = (y_plane, u_plane, v_plane);
+    write_bands_par(
+        planes,
+        dim,
+        strides,
+        num_threads,
+        RGBA_PIXEL_LEN,
+        target,
+        |(y, u, v), dim, target| {
+            write_rgba8(y, u, v, dim, strides, color_space, target);
+        },
+    );
+}
+
+/// Splits the image into one horizontal band per thread and calls `write` for each of them.
+///
+/// Bands start at even rows, so each one has its own chroma rows. With a single band, `write` is called on
+/// the current thread.
+fn write_bands_par<'a>(
+    planes: (&'a [u8], &'a [u8], &'a [u8]),
+    dim: (usize, usize),
+    strides: (usize, usize, usize),
+    num_threads: usize,
+    pixel_len: usize,
+    target: &mut [u8],
+    write: impl Fn((&'a [u8], &'a [u8], &'a [u8]), (usize, usize), &mut [u8]) + Sync,
+) {
+    let (width, height) = dim;
+    let rows_per_band = height.div_ceil(num_threads.max(1)).next_multiple_of(2).max(2);
+    let bytes_per_row = width * pixel_len;
+
+    if rows_per_band >= height {
+        write(planes, dim, target);
+        return;
+    }
+
+    let (y_plane, u_plane, v_plane) = planes;
+    let write = &write;
+
+    std::thread::scope(|s| {
+        for (i, band) in target[..height * bytes_per_row]
+            .chunks_mut(rows_per_band * bytes_per_row)
+            .enumerate()
+        {
+            let row = i * rows_per_band;
+            let band_planes = (
+                &y_plane[row * strides.0..],
+                &u_plane[row / 2 * strides.1..],
+                &v_plane[row / 2 * strides.2..],
+            );
+            let band_dim = (width, band.len() / bytes_per_row);
+
+            s.spawn(move || write(band_planes, band_dim, band));
+        }
+    });
+}
+
+/// Write packed 8 bit pixels in the given layout from YUV420, using f32x8 SIMD where possible.
+///
+/// Alpha channels are set to 255. Unlike [`write_rgb8`] this works for any image size.
+#[allow(clippy::many_single_char_names, clippy::too_many_arguments)]
+pub fn write_rgb8_layout(
+    y_plane: &[u8],
+    u_plane: &[u8],
+    v_plane: &[u8],
+    dim: (usize, usize),
+    strides: (usize, usize, usize),
+    layout: RGB8Layout,
+    color_space: ColorSpace,
+    target: &mut [u8],
+) {
+    let planes = (y_plane, u_plane, v_plane);
+
+    match layout {
+        RGB8Layout::Rgb => write_packed_f32x8(planes, dim, strides, color_space, target, |[r, g, b]| [r, g, b]),
+        RGB8Layout::Bgr => write_packed_f32x8(planes, dim, strides, color_space, target, |[r, g, b]| [b, g, r]),
+        RGB8Layout::Rgba => write_packed_f32x8(planes, dim, strides, color_space, target, |[r, g, b]| [r, g, b, 255]),
+        RGB8Layout::Bgra => write_packed_f32x8(planes, dim, strides, color_space, target, |[r, g, b]| [b, g, r, 255]),
+        RGB8Layout::Argb => write_packed_f32x8(planes, dim, strides, color_space, target, |[r, g, b]| [255, r, g, b]),
+        RGB8Layout::Abgr => write_packed_f32x8(planes, dim, strides, color_space, target, |[r, g, b]| [255, b, g, r]),
+    }
+}
+
+/// Write RGB565 pixels (5 bits red in the high bits, 6 bits green, 5 bits blue) from YUV420, using f32x8
+/// SIMD where possible.
+pub fn write_rgb565(
+    y_plane: &[u8],
+    u_plane: &[u8],
+    v_plane: &[u8],
+    dim: (usize, usize),
+    strides: (usize, usize, usize),
+    color_space: ColorSpace,
+    target: &mut [u16],
+) {
+    let planes = (y_plane, u_plane, v_plane);
 
-            // Convert limited range YUV to RGB
-            // https://en.wikipedia.org/wiki/YCbCr#ITU-R_BT.601_conversion
-            let y_mul = Y_MUL * (f32::from(y_plane[base_y]) - 16.0);
-            let u = f32::from(u_plane[base_u]) - 128.0;
-            let v = f32::from(v_plane[base_v]) - 128.0;
+    write_packed_f32x8(planes, dim, strides, color_space, target, |[r, g, b]| {
+        [(u16::from(r >> 3) << 11) | (u16::from(g >> 2) << 5) | u16::from(b >> 3)]
+    });
+}
+
+/// Write `N` elements per pixel from YUV420, as returned by `pixel` for its `[r, g, b]` value.
+///
+/// Each row is converted with f32x8 SIMD, except for the last `width % 8` pixels.
+fn write_packed_f32x8<T: Copy, const N: usize>(
+    planes: (&[u8], &[u8], &[u8]),
+    dim: (usize, usize),
+    strides: (usize, usize, usize),
+    color_space: ColorSpace,
+    target: &mut [T],
+    pixel: impl Fn([u8; 3]) -> [T; N],
+) {
+    const STEP: usize = 8;
+    const UV_STEP: usize = STEP / 2;
+
+    let coefficients = YuvToRgb::new(color_space);
+    let (y_plane, u_plane, v_plane) = planes;
+    let (width, height) = dim;
+    let simd_width = width - width % STEP;
+
+    if width == 0 {
+        return;
+    }
+
+    for (y, row_target) in target[..width * height * N].chunks_exact_mut(width * N).enumerate() {
+        let y_row = &y_plane[y * strides.0..][..width];
+        let u_row = &u_plane[y / 2 * strides.1..][..width.div_ceil(2)];
+        let v_row = &v_plane[y / 2 * strides.2..][..width.div_ceil(2)];
+        let (simd_target, scalar_target) = row_target.split_at_mut(simd_width * N);
+
+        for (x, pixels) in simd_target.chunks_exact_mut(STEP * N).enumerate() {
+            // Convert slices to arrays (MSRV 1.85 compatible)
+            let y: [u8; STEP] = y_row[x * STEP..][..STEP].try_into().unwrap();
+            let u: [u8; UV_STEP] = u_row[x * UV_STEP..][..UV_STEP].try_into().unwrap();
+            let v: [u8; UV_STEP] = v_row[x * UV_STEP..][..UV_STEP].try_into().unwrap();
+
+            for (rgb, target) in coefficients.rgb_f32x8(y, u, v).into_iter().zip(pixels.chunks_exact_mut(N)) {
+                target.copy_from_slice(&pixel(rgb));
+            }
+        }
 
-            rgb_pixel[0] = RV_MUL.mul_add(v, y_mul) as u8;
-            rgb_pixel[1] = GV_MUL.mul_add(v, GU_MUL.mul_add(u, y_mul)) as u8;
-            rgb_pixel[2] = BU_MUL.mul_add(u, y_mul) as u8;
+        for (x, target) in (simd_width..width).zip(scalar_target.chunks_exact_mut(N)) {
+            target.copy_from_slice(&pixel(coefficients.rgb(y_row[x], u_row[x / 2], v_row[x / 2])));
         }
     }
 }
 
 /// Write RGB8 data from YUV420 using scalar (non SIMD) math.
 #[allow(dead_code)]
-pub fn write_rgb8_scalar_par(
+pub fn write_rgb8_scalar(
     y_plane: &[u8],
     u_plane: &[u8],
     v_plane: &[u8],
     dim: (usize, usize),
     strides: (usize, usize, usize),
+    color_space: ColorSpace,
     target: &mut [u8],
 ) {
-    // distribute data across threads
-    // the call to `std::thread::available_parallelism()` takes quite long (77 micros for me)
-    const NUM_THREADS: usize = 4;
-
-    // split output slices
-    let pixels_per_thread = (dim.0 * dim.1 * RGB_PIXEL_LEN) / NUM_THREADS;
-    let target_chunks = target.chunks_mut(pixels_per_thread);
-
-    // input planes
-    let rows_per_thread = dim.1 / NUM_THREADS;
-    let mut row_indices: Vec<(usize, usize)> = (0..NUM_THREADS)
-        .map(|i| (i * rows_per_thread, (i + 1) * rows_per_thread))
-        .collect();
-    // add more rows to the last thread, if not able to distribute evenly
-    // --> mirror behavior from chunks_mut
-    row_indices[NUM_THREADS - 1].1 += dim.1 % NUM_THREADS;
+    let coefficients = YuvToRgb::new(color_space);
 
-    std::thread::scope(|s| {
-        for (target, (row_start, row_end)) in target_chunks.zip(row_indices) {
-            s.spawn(move || {
-                for y in row_start..row_end {
-                    for x in 0..dim.0 {
-                        let base_tgt = ((y - row_start) * dim.0 + x) * RGB_PIXEL_LEN;
-                        let base_y = y * strides.0 + x;
-                        let base_u = (y / 2 * strides.1) + (x / 2);
-                        let base_v = (y / 2 * strides.2) + (x / 2);
-
-                        let rgb_pixel = &mut target[base_tgt..base_tgt + RGB_PIXEL_LEN];
-
-                        // Convert limited range YUV to RGB
-                        // https://en.wikipedia.org/wiki/YCbCr#ITU-R_BT.601_conversion
-                        let y_mul = Y_MUL * (f32::from(y_plane[base_y]) - 16.0);
-                        let u = f32::from(u_plane[base_u]) - 128.0;
-                        let v = f32::from(v_plane[base_v]) - 128.0;
-
-                        rgb_pixel[0] = RV_MUL.mul_add(v, y_mul) as u8;
-                        rgb_pixel[1] = GV_MUL.mul_add(v, GU_MUL.mul_add(u, y_mul)) as u8;
-                        rgb_pixel[2] = BU_MUL.mul_add(u, y_mul) as u8;
-                    }
-                }
-            });
+    for y in 0..dim.1 {
+        for x in 0..dim.0 {
+            let base_tgt = (y * dim.0 + x) * RGB_PIXEL_LEN;
+            let base_y = y * strides.0 + x;
+            let base_u = (y / 2 * strides.1) + (x / 2);
+            let base_v = (y / 2 * strides.2) + (x / 2);
+
+            let rgb_pixel = &mut target[base_tgt..base_tgt + RGB_PIXEL_LEN];
+
+            rgb_pixel.copy_from_slice(&coefficients.rgb(y_plane[base_y], u_plane[base_u], v_plane[base_v]));
         }
-    });
+    }
 }
 
 /// Write RGB8 data from YUV420 using f32x8 SIMD.
@@ -103,8 +356,11 @@ pub fn write_rgb8_f32x8(
     v_plane: &[u8],
     dim: (usize, usize),
     strides: (usize, usize, usize),
+    color_space: ColorSpace,
     target: &mut [u8],
 ) {
+    let coefficients = YuvToRgb::new(color_space);
+
     // this assumes we are decoding YUV420
     assert_eq!(y_plane.len(), u_plane.len() * 4);
     assert_eq!(y_plane.len(), v_plane.len() * 4);
@@ -127,7 +383,7 @@ pub fn write_rgb8_f32x8(
         // calculate first RGB row
         let base_tgt = 2 * y * rgb_bytes_per_row;
         let row_target = &mut target[base_tgt..base_tgt + rgb_bytes_per_row];
-        write_rgb8_f32x8_row(y_row, u_row, v_row, row_target);
+        write_rgb8_f32x8_row(y_row, u_row, v_row, &coefficients, row_target);
 
         // load Y values for second row
         let base_y = (2 * y + 1) * strides.0;
@@ -136,72 +392,10 @@ pub fn write_rgb8_f32x8(
         // calculate second RGB row
         let base_tgt = (2 * y + 1) * rgb_bytes_per_row;
         let row_target = &mut target[base_tgt..(base_tgt + rgb_bytes_per_row)];
-        write_rgb8_f32x8_row(y_row, u_row, v_row, row_target);
+        write_rgb8_f32x8_row(y_row, u_row, v_row, &coefficients, row_target);
     }
 }
 
-/// Write RGB8 data from YUV420 using f32x8 SIMD.
-#[allow(clippy::identity_op)]
-#[allow(dead_code)] // usabe TBD
-pub fn write_rgb8_f32x8_par(
-    y_plane: &[u8],
-    u_plane: &[u8],
-    v_plane: &[u8],
-    dim: (usize, usize),
-    strides: (usize, usize, usize),
-    target: &mut [u8],
-) {
-    // the call to `std::thread::available_parallelism()` takes quite long (77 micros for me)
-    const NUM_THREADS: usize = 4;
-
-    // this assumes we are decoding YUV420
-    assert_eq!(y_plane.len(), u_plane.len() * 4);
-    assert_eq!(y_plane.len(), v_plane.len() * 4);
-    assert_eq!(dim.0 % 8, 0);
-
-    let (width, _height) = dim;
-    let rgb_bytes_per_row: usize = RGB_PIXEL_LEN * width; // rgb pixel size in bytes
-
-    // distribute data across threads
-    let rows_per_thread = dim.1 / NUM_THREADS;
-    let chunk_sz = (dim.0 * dim.1 * RGB_PIXEL_LEN) / NUM_THREADS;
-    let target_chunks = target.chunks_mut(chunk_sz).enumerate();
-
-    std::thread::scope(|s| {
-        for (i, target) in target_chunks {
-            s.spawn(move || {
-                let range = 0..(rows_per_thread / 2);
-                let offset = i * (rows_per_thread / 2);
-                for y in range {
-                    // load U and V values for two rows of pixels
-                    let base_u = (y + offset) * strides.1;
-                    let u_row = &u_plane[base_u..base_u + strides.1];
-                    let base_v = (y + offset) * strides.2;
-                    let v_row = &v_plane[base_v..base_v + strides.2];
-
-                    // load Y values for first row
-                    let base_y = 2 * (y + offset) * strides.0;
-                    let y_row = &y_plane[base_y..base_y + strides.0];
-
-                    // calculate first RGB row
-                    let base_tgt = 2 * y * rgb_bytes_per_row;
-                    let row_target = &mut target[base_tgt..base_tgt + rgb_bytes_per_row];
-                    write_rgb8_f32x8_row(y_row, u_row, v_row, row_target);
-
-                    // load Y values for second row
-                    let base_y = (2 * (y + offset) + 1) * strides.0;
-                    let y_row = &y_plane[base_y..base_y + strides.0];
-
-                    // calculate second RGB row
-                    let base_tgt = (2 * y + 1) * rgb_bytes_per_row;
-                    let row_target = &mut target[base_tgt..(base_tgt + rgb_bytes_per_row)];
-                    write_rgb8_f32x8_row(y_row, u_row, v_row, row_target);
-                }
-            });
-        }
-    });
-}
-
 /// Converts float values into f32x8 SIMD lanes.
 ///
 /// If you have a (pixel buffer) slice of at least 8 f32 values like so `[012345678...]`, this function
@@ -212,7 +406,12 @@ pub fn write_rgb8_f32x8_par(
 /// V: [00112233...]
 #[allow(clippy::inline_always)]
 #[inline(always)]
-fn pack_into_yuv420_f32x8(y_row: &[u8; 8], u_row: &[u8; 4], v_row: &[u8; 4]) -> (wide::f32x8, wide::f32x8, wide::f32x8) {
+fn pack_into_yuv420_f32x8(
+    y_row: &[u8; 8],
+    u_row: &[u8; 4],
+    v_row: &[u8; 4],
+    y_offset: f32,
+) -> (wide::f32x8, wide::f32x8, wide::f32x8) {
     let [y0, y1, y2, y3, y4, y5, y6, y7] = *y_row;
     let y_pack = wide::f32x8::from([
         f32::from(y0),
@@ -223,7 +422,7 @@ fn pack_into_yuv420_f32x8(y_row: &[u8; 8], u_row: &[u8; 4], v_row: &[u8; 4]) ->
         f32::from(y5),
         f32::from(y6),
         f32::from(y7),
-    ]) - 16.0;
+    ]) - y_offset;
 
     let [u0, u1, u2, u3] = *u_row;
     let u_pack = wide::f32x8::from([
@@ -256,7 +455,7 @@ fn pack_into_yuv420_f32x8(y_row: &[u8; 8], u_row: &[u8; 4], v_row: &[u8; 4]) ->
 #[allow(clippy::inline_always)]
 #[allow(clippy::similar_names)]
 #[inline(always)]
-fn write_rgb8_f32x8_row(y_row: &[u8], u_row: &[u8], v_row: &[u8], target: &mut [u8]) {
+fn write_rgb8_f32x8_row(y_row: &[u8], u_row: &[u8], v_row: &[u8], coefficients: &YuvToRgb, target: &mut [u8]) {
     const STEP: usize = 8;
     const UV_STEP: usize = STEP / 2;
     const TGT_STEP: usize = STEP * RGB_PIXEL_LEN;
@@ -265,11 +464,11 @@ fn write_rgb8_f32x8_row(y_row: &[u8], u_row: &[u8], v_row: &[u8], target: &mut [
     assert_eq!(y_row.len(), u_row.len() * 2);
     assert_eq!(y_row.len(), v_row.len() * 2);
 
-    let y_mul = wide::f32x8::splat(Y_MUL);
-    let rv_mul = wide::f32x8::splat(RV_MUL);
-    let gu_mul = wide::f32x8::splat(GU_MUL);
-    let gv_mul = wide::f32x8::splat(GV_MUL);
-    let bu_mul = wide::f32x8::splat(BU_MUL);
+    let y_mul = wide::f32x8::splat(coefficients.y_mul);
+    let rv_mul = wide::f32x8::splat(coefficients.rv_mul);
+    let gu_mul = wide::f32x8::splat(coefficients.gu_mul);
+    let gv_mul = wide::f32x8::splat(coefficients.gv_mul);
+    let bu_mul = wide::f32x8::splat(coefficients.bu_mul);
 
     let upper_bound = wide::f32x8::splat(255.0);
     let lower_bound = wide::f32x8::splat(0.0);
@@ -290,7 +489,7 @@ fn write_rgb8_f32x8_row(y_row: &[u8], u_row: &[u8], v_row: &[u8], target: &mut [
         let y: &[u8; STEP] = y.try_into().unwrap();
         let u: &[u8; UV_STEP] = u.try_into().unwrap();
         let v: &[u8; UV_STEP] = v.try_into().unwrap();
-        let (y_pack, u_pack, v_pack) = pack_into_yuv420_f32x8(y, u, v);
+        let (y_pack, u_pack, v_pack) = pack_into_yuv420_f32x8(y, u, v, coefficients.y_offset);
         let y_mul: wide::f32x8 = y_pack * y_mul;
 
         let r_pack = v_pack.mul_add(rv_mul, y_mul);
@@ -320,8 +519,11 @@ pub fn write_rgba8_scalar(
     v_plane: &[u8],
     dim: (usize, usize),
     strides: (usize, usize, usize),
+    color_space: ColorSpace,
     target: &mut [u8],
 ) {
+    let coefficients = YuvToRgb::new(color_space);
+
     for y in 0..dim.1 {
         for x in 0..dim.0 {
             let base_tgt = (y * dim.0 + x) * RGBA_PIXEL_LEN;
@@ -331,15 +533,7 @@ pub fn write_rgba8_scalar(
 
             let rgba_pixel = &mut target[base_tgt..base_tgt + RGBA_PIXEL_LEN];
 
-            // Convert limited range YUV to RGB
-            // https://en.wikipedia.org/wiki/YCbCr#ITU-R_BT.601_conversion
-            let y_mul = Y_MUL * (f32::from(y_plane[base_y]) - 16.0);
-            let u = f32::from(u_plane[base_u]) - 128.0;
-            let v = f32::from(v_plane[base_v]) - 128.0;
-
-            rgba_pixel[0] = RV_MUL.mul_add(v, y_mul) as u8;
-            rgba_pixel[1] = GV_MUL.mul_add(v, GU_MUL.mul_add(u, y_mul)) as u8;
-            rgba_pixel[2] = BU_MUL.mul_add(u, y_mul) as u8;
+            rgba_pixel[..3].copy_from_slice(&coefficients.rgb(y_plane[base_y], u_plane[base_u], v_plane[base_v]));
             rgba_pixel[3] = 255;
         }
     }
@@ -353,8 +547,11 @@ pub fn write_rgba8_f32x8(
     v_plane: &[u8],
     dim: (usize, usize),
     strides: (usize, usize, usize),
+    color_space: ColorSpace,
     target: &mut [u8],
 ) {
+    let coefficients = YuvToRgb::new(color_space);
+
     // this assumes we are decoding YUV420
     assert_eq!(y_plane.len(), u_plane.len() * RGBA_PIXEL_LEN);
     assert_eq!(y_plane.len(), v_plane.len() * RGBA_PIXEL_LEN);
@@ -377,7 +574,7 @@ pub fn write_rgba8_f32x8(
         // calculate first RGB row
         let base_tgt = 2 * y * rgba_bytes_per_row;
         let row_target = &mut target[base_tgt..base_tgt + rgba_bytes_per_row];
-        write_rgba8_f32x8_row(y_row, u_row, v_row, row_target);
+        write_rgba8_f32x8_row(y_row, u_row, v_row, &coefficients, row_target);
 
         // load Y values for second row
         let base_y = (2 * y + 1) * strides.0;
@@ -386,7 +583,7 @@ pub fn write_rgba8_f32x8(
         // calculate second RGB row
         let base_tgt = (2 * y + 1) * rgba_bytes_per_row;
         let row_target = &mut target[base_tgt..(base_tgt + rgba_bytes_per_row)];
-        write_rgba8_f32x8_row(y_row, u_row, v_row, row_target);
+        write_rgba8_f32x8_row(y_row, u_row, v_row, &coefficients, row_target);
     }
 }
 
@@ -394,7 +591,7 @@ pub fn write_rgba8_f32x8(
 #[allow(clippy::inline_always)]
 #[allow(clippy::similar_names)]
 #[inline(always)]
-fn write_rgba8_f32x8_row(y_row: &[u8], u_row: &[u8], v_row: &[u8], target: &mut [u8]) {
+fn write_rgba8_f32x8_row(y_row: &[u8], u_row: &[u8], v_row: &[u8], coefficients: &YuvToRgb, target: &mut [u8]) {
     const STEP: usize = 8;
     const UV_STEP: usize = STEP / 2;
     const TGT_STEP: usize = STEP * RGBA_PIXEL_LEN;
@@ -402,11 +599,11 @@ fn write_rgba8_f32x8_row(y_row: &[u8], u_row: &[u8], v_row: &[u8], target: &mut
     assert_eq!(y_row.len(), u_row.len() * 2);
     assert_eq!(y_row.len(), v_row.len() * 2);
 
-    let y_mul = wide::f32x8::splat(Y_MUL);
-    let rv_mul = wide::f32x8::splat(RV_MUL);
-    let gu_mul = wide::f32x8::splat(GU_MUL);
-    let gv_mul = wide::f32x8::splat(GV_MUL);
-    let bu_mul = wide::f32x8::splat(BU_MUL);
+    let y_mul = wide::f32x8::splat(coefficients.y_mul);
+    let rv_mul = wide::f32x8::splat(coefficients.rv_mul);
+    let gu_mul = wide::f32x8::splat(coefficients.gu_mul);
+    let gv_mul = wide::f32x8::splat(coefficients.gv_mul);
+    let bu_mul = wide::f32x8::splat(coefficients.bu_mul);
 
     let upper_bound = wide::f32x8::splat(255.0);
     let lower_bound = wide::f32x8::splat(0.0);
@@ -427,7 +624,7 @@ fn write_rgba8_f32x8_row(y_row: &[u8], u_row: &[u8], v_row: &[u8], target: &mut
         let y: &[u8; STEP] = y.try_into().unwrap();
         let u: &[u8; UV_STEP] = u.try_into().unwrap();
         let v: &[u8; UV_STEP] = v.try_into().unwrap();
-        let (y_pack, u_pack, v_pack) = pack_into_yuv420_f32x8(y, u, v);
+        let (y_pack, u_pack, v_pack) = pack_into_yuv420_f32x8(y, u, v, coefficients.y_offset);
         let y_mul: wide::f32x8 = y_pack * y_mul;
 
         let r_pack = v_pack.mul_add(rv_mul, y_mul);
@@ -454,27 +651,28 @@ fn write_rgba8_f32x8_row(y_row: &[u8], u_row: &[u8], v_row: &[u8], target: &mut
 mod test {
     use crate::OpenH264API;
     use crate::decoder::{Decoder, DecoderConfig};
-    use crate::formats::YUVSource;
     use crate::formats::yuv2rgb::{
-        write_rgb8_f32x8, write_rgb8_f32x8_par, write_rgb8_scalar, write_rgb8_scalar_par, write_rgba8_f32x8, write_rgba8_scalar,
+        write_rgb8, write_rgb8_f32x8, write_rgb8_layout, write_rgb8_par, write_rgb8_scalar, write_rgb565, write_rgba8,
+        write_rgba8_f32x8, write_rgba8_par, write_rgba8_scalar,
     };
+    use crate::formats::{ColorMatrix, ColorSpace, RGB8Layout, YUVSource};
 
     #[test]
     fn write_rgb8_scalar_range() {
         let mut tgt = vec![0; 3];
-        write_rgb8_scalar(&[235], &[128], &[128], (1, 1), (1, 1, 1), &mut tgt);
+        write_rgb8_scalar(&[235], &[128], &[128], (1, 1), (1, 1, 1), ColorSpace::default(), &mut tgt);
         assert_eq!(tgt, [255, 255, 255]);
 
-        write_rgb8_scalar(&[16], &[128], &[128], (1, 1), (1, 1, 1), &mut tgt);
+        write_rgb8_scalar(&[16], &[128], &[128], (1, 1), (1, 1, 1), ColorSpace::default(), &mut tgt);
         assert_eq!(tgt, [0, 0, 0]);
 
-        write_rgb8_scalar(&[235], &[240], &[240], (1, 1), (1, 1, 1), &mut tgt);
+        write_rgb8_scalar(&[235], &[240], &[240], (1, 1), (1, 1, 1), ColorSpace::default(), &mut tgt);
         assert_eq!(tgt, [255, 133, 255]);
 
-        write_rgb8_scalar(&[235], &[0], &[240], (1, 1), (1, 1, 1), &mut tgt);
+        write_rgb8_scalar(&[235], &[0], &[240], (1, 1), (1, 1, 1), ColorSpace::default(), &mut tgt);
         assert_eq!(tgt, [255, 227, 0]);
 
-        write_rgb8_scalar(&[235], &[240], &[0], (1, 1), (1, 1, 1), &mut tgt);
+        write_rgb8_scalar(&[235], &[240], &[0], (1, 1), (1, 1, 1), ColorSpace::default(), &mut tgt);
         assert_eq!(tgt, [50, 255, 255]);
     }
 
@@ -493,10 +691,26 @@ mod test {
 
         let tgt = &mut rgb[0..rgb_len];
 
-        write_rgb8_scalar(yuv.y(), yuv.u(), yuv.v(), yuv.dimensions(), yuv.strides(), tgt);
+        write_rgb8_scalar(
+            yuv.y(),
+            yuv.u(),
+            yuv.v(),
+            yuv.dimensions(),
+            yuv.strides(),
+            ColorSpace::default(),
+            tgt,
+        );
 
         let mut tgt2 = vec![0; tgt.len()];
-        write_rgb8_f32x8(yuv.y(), yuv.u(), yuv.v(), yuv.dimensions(), yuv.strides(), &mut tgt2);
+        write_rgb8_f32x8(
+            yuv.y(),
+            yuv.u(),
+            yuv.v(),
+            yuv.dimensions(),
+            yuv.strides(),
+            ColorSpace::default(),
+            &mut tgt2,
+        );
 
         assert_eq!(tgt, tgt2);
     }
@@ -516,10 +730,26 @@ mod test {
 
         let tgt = &mut rgb[0..rgb_len];
 
-        write_rgba8_scalar(yuv.y(), yuv.u(), yuv.v(), yuv.dimensions(), yuv.strides(), tgt);
+        write_rgba8_scalar(
+            yuv.y(),
+            yuv.u(),
+            yuv.v(),
+            yuv.dimensions(),
+            yuv.strides(),
+            ColorSpace::default(),
+            tgt,
+        );
 
         let mut tgt2 = vec![0; tgt.len()];
-        write_rgba8_f32x8(yuv.y(), yuv.u(), yuv.v(), yuv.dimensions(), yuv.strides(), &mut tgt2);
+        write_rgba8_f32x8(
+            yuv.y(),
+            yuv.u(),
+            yuv.v(),
+            yuv.dimensions(),
+            yuv.strides(),
+            ColorSpace::default(),
+            &mut tgt2,
+        );
 
         assert_eq!(tgt, tgt2);
     }
@@ -539,34 +769,231 @@ mod test {
 
         let tgt = &mut rgb[0..rgb_len];
 
-        write_rgb8_scalar(yuv.y(), yuv.u(), yuv.v(), yuv.dimensions(), yuv.strides(), tgt);
+        write_rgb8_scalar(
+            yuv.y(),
+            yuv.u(),
+            yuv.v(),
+            yuv.dimensions(),
+            yuv.strides(),
+            ColorSpace::default(),
+            tgt,
+        );
 
         let mut tgt2 = vec![0; tgt.len()];
-        write_rgb8_scalar_par(yuv.y(), yuv.u(), yuv.v(), yuv.dimensions(), yuv.strides(), &mut tgt2);
+        write_rgb8_par(
+            yuv.y(),
+            yuv.u(),
+            yuv.v(),
+            yuv.dimensions(),
+            yuv.strides(),
+            ColorSpace::default(),
+            4,
+            &mut tgt2,
+        );
 
         assert_eq!(tgt, tgt2);
     }
 
     #[test]
-    fn write_rgb8_f32x8_par_matches_scalar() {
+    fn write_rgba8_par_matches_scalar() {
         let source = include_bytes!("../../tests/data/single_512x512_cavlc.h264");
 
         let api = OpenH264API::from_source();
         let config = DecoderConfig::default();
         let mut decoder = Decoder::with_api_config(api, config).unwrap();
 
-        let mut rgb = vec![0; 2000 * 2000 * 3];
+        let mut rgba = vec![0; 2000 * 2000 * 4];
         let yuv = decoder.decode(&source[..]).unwrap().unwrap();
         let dim = yuv.dimensions();
-        let rgb_len = dim.0 * dim.1 * 3;
-
-        let tgt = &mut rgb[0..rgb_len];
-
-        write_rgb8_scalar(yuv.y(), yuv.u(), yuv.v(), yuv.dimensions(), yuv.strides(), tgt);
+        let rgba_len = dim.0 * dim.1 * 4;
+
+        let tgt = &mut rgba[0..rgba_len];
+
+        write_rgba8_scalar(
+            yuv.y(),
+            yuv.u(),
+            yuv.v(),
+            yuv.dimensions(),
+            yuv.strides(),
+            ColorSpace::default(),
+            tgt,
+        );
 
         let mut tgt2 = vec![0; tgt.len()];
-        write_rgb8_f32x8_par(yuv.y(), yuv.u(), yuv.v(), yuv.dimensions(), yuv.strides(), &mut tgt2);
+        write_rgba8_par(
+            yuv.y(),
+            yuv.u(),
+            yuv.v(),
+            yuv.dimensions(),
+            yuv.strides(),
+            ColorSpace::default(),
+            4,
+            &mut tgt2,
+        );
 
         assert_eq!(tgt, tgt2);
     }
+
+    #[test]
+    fn write_rgb8_color_spaces() {
+        let mut tgt = vec![0; 3];
+        let full = |matrix| ColorSpace::new(matrix, true);
+
+        for matrix in [ColorMatrix::Bt601, ColorMatrix::Bt709, ColorMatrix::Bt2020] {
+            write_rgb8_scalar(&[255], &[128], &[128], (1, 1), (1, 1, 1), full(matrix), &mut tgt);
+            assert_eq!(tgt, [255, 255, 255]);
+
+            write_rgb8_scalar(&[0], &[128], &[128], (1, 1), (1, 1, 1), full(matrix), &mut tgt);
+            assert_eq!(tgt, [0, 0, 0]);
+
+            write_rgb8_scalar(
+                &[235],
+                &[128],
+                &[128],
+                (1, 1),
+                (1, 1, 1),
+                ColorSpace::new(matrix, false),
+                &mut tgt,
+            );
+            assert_eq!(tgt, [255, 255, 255]);
+        }
+
+        // Pure red as encoded by BT.709 limited range.
+        write_rgb8_scalar(&[63], &[102], &[240], (1, 1), (1, 1, 1), ColorSpace::bt709(), &mut tgt);
+        assert_eq!(tgt, [255, 0, 0]);
+    }
+
+    #[test]
+    fn write_rgb8_f32x8_matches_scalar_for_color_spaces() {
+        let (w, h) = (24, 6);
+        let y_plane = (0..w * h).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+        let u_plane = (0..w * h / 4).map(|i| (i * 53 % 241) as u8).collect::<Vec<_>>();
+        let v_plane = (0..w * h / 4).map(|i| (i * 31 % 239) as u8).collect::<Vec<_>>();
+        let strides = (w, w / 2, w / 2);
+
+        // Allow a difference of 1, as rounding may differ between CPU architectures.
+        let almost_equal = |a: &[u8], b: &[u8]| a.iter().zip(b).all(|(a, b)| a.abs_diff(*b) <= 1);
+
+        for matrix in [ColorMatrix::Bt601, ColorMatrix::Bt709, ColorMatrix::Bt2020] {
+            for color_space in [ColorSpace::new(matrix, false), ColorSpace::new(matrix, true)] {
+                let (mut scalar, mut simd) = (vec![0; w * h * 3], vec![0; w * h * 3]);
+                write_rgb8_scalar(&y_plane, &u_plane, &v_plane, (w, h), strides, color_space, &mut scalar);
+                write_rgb8_f32x8(&y_plane, &u_plane, &v_plane, (w, h), strides, color_space, &mut simd);
+                assert!(almost_equal(&scalar, &simd), "{color_space:?}");
+
+                let (mut scalar, mut simd) = (vec![0; w * h * 4], vec![0; w * h * 4]);
+                write_rgba8_scalar(&y_plane, &u_plane, &v_plane, (w, h), strides, color_space, &mut scalar);
+                write_rgba8_f32x8(&y_plane, &u_plane, &v_plane, (w, h), strides, color_space, &mut simd);
+                assert!(almost_equal(&scalar, &simd), "{color_space:?}");
+            }
+        }
+    }
+
+    #[test]
+    #[allow(clippy::similar_names)]
+    fn write_par_matches_single_threaded() {
+        for (w, h) in [(24_usize, 6_usize), (16, 10), (13, 9), (8, 2), (7, 1)] {
+            let (w2, h2) = (w.div_ceil(2), h.div_ceil(2));
+            let y_plane = (0..w * h).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+            let u_plane = (0..w2 * h2).map(|i| (i * 53 % 241) as u8).collect::<Vec<_>>();
+            let v_plane = (0..w2 * h2).map(|i| (i * 31 % 239) as u8).collect::<Vec<_>>();
+            let strides = (w, w2, w2);
+            let color_space = ColorSpace::bt709();
+
+            let mut rgb = vec![0; w * h * 3];
+            let mut rgba = vec![0; w * h * 4];
+            write_rgb8(&y_plane, &u_plane, &v_plane, (w, h), strides, color_space, &mut rgb);
+            write_rgba8(&y_plane, &u_plane, &v_plane, (w, h), strides, color_space, &mut rgba);
+
+            for num_threads in [0, 1, 2, 3, 4, 16] {
+                let mut rgb_par = vec![0; w * h * 3];
+                let mut rgba_par = vec![0; w * h * 4];
+                write_rgb8_par(
+                    &y_plane,
+                    &u_plane,
+                    &v_plane,
+                    (w, h),
+                    strides,
+                    color_space,
+                    num_threads,
+                    &mut rgb_par,
+                );
+                write_rgba8_par(
+                    &y_plane,
+                    &u_plane,
+                    &v_plane,
+                    (w, h),
+                    strides,
+                    color_space,
+                    num_threads,
+                    &mut rgba_par,
+                );
+
+                assert_eq!(rgb, rgb_par, "{w}x{h} with {num_threads} threads");
+                assert_eq!(rgba, rgba_par, "{w}x{h} with {num_threads} threads");
+            }
+        }
+    }
+
+    #[test]
+    fn write_rgb8_layout_matches_scalar() {
+        for (w, h) in [(24_usize, 6_usize), (13, 9), (7, 1)] {
+            let (w2, h2) = (w.div_ceil(2), h.div_ceil(2));
+            let y_plane = (0..w * h).map(|i| (i * 97 % 251) as u8).collect::<Vec<_>>();
+            let u_plane = (0..w2 * h2).map(|i| (i * 53 % 241) as u8).collect::<Vec<_>>();
+            let v_plane = (0..w2 * h2).map(|i| (i * 31 % 239) as u8).collect::<Vec<_>>();
+            let strides = (w, w2, w2);
+            let color_space = ColorSpace::bt709();
+
+            let mut rgb = vec![0; w * h * 3];
+            write_rgb8_scalar(&y_plane, &u_plane, &v_plane, (w, h), strides, color_space, &mut rgb);
+
+            for (layout, channels) in [
+                (RGB8Layout::Rgb, [Some(0), Some(1), Some(2), None]),
+                (RGB8Layout::Bgr, [Some(2), Some(1), Some(0), None]),
+                (RGB8Layout::Rgba, [Some(0), Some(1), Some(2), Some(3)]),
+                (RGB8Layout::Bgra, [Some(2), Some(1), Some(0), Some(3)]),
+                (RGB8Layout::Argb, [Some(1), Some(2), Some(3), Some(0)]),
+                (RGB8Layout::Abgr, [Some(3), Some(2), Some(1), Some(0)]),
+            ] {
+                let len = layout.bytes_per_pixel();
+                let mut target = vec![0; w * h * len];
+                write_rgb8_layout(&y_plane, &u_plane, &v_plane, (w, h), strides, layout, color_space, &mut target);
+
+                for (expected, pixel) in rgb.chunks_exact(3).zip(target.chunks_exact(len)) {
+                    for (c, channel) in channels.into_iter().enumerate() {
+                        let Some(channel) = channel else { continue };
+                        let expected = expected.get(c).copied().unwrap_or(255);
+
+                        // Allow a difference of 1, as rounding may differ between CPU architectures.
+                        assert!(pixel[channel].abs_diff(expected) <= 1, "{layout:?} at {w}x{h}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn write_rgb565_packs_channels() {
+        let mut target = [0];
+
+        // Full range white, black, red and green, as one 1x1 image each.
+        for ((y, u, v), expected) in [
+            ((255, 128, 128), 0xFFFF),
+            ((0, 128, 128), 0x0000),
+            ((76, 85, 255), 0xF800),
+            ((150, 44, 21), 0x07E0),
+        ] {
+            write_rgb565(
+                &[y],
+                &[u],
+                &[v],
+                (1, 1),
+                (1, 1, 1),
+                ColorSpace::bt601().full_range(true),
+                &mut target,
+            );
+            assert_eq!(target[0], expected, "YUV: {:?}", (y, u, v));
+        }
+    }
 }
@@ -3,7 +3,10 @@
 #![doc = include_str!("../README.md")]
 
 mod error;
+mod sps;
 mod time;
+#[cfg(feature = "log")]
+mod trace;
 mod utils;
 
 pub mod decoder;
@@ -0,0 +1,336 @@
+//! Minimal sequence parameter set parser, extracting the VUI fields OpenH264 doesn't report.
+//!
+//! See ITU-T H.264 sections 7.3.2.1.1 (SPS) and E.1.1 (VUI) for the syntax.
+
+use crate::decoder::VuiInfo;
+use crate::encoder::{ColorPrimaries, MatrixCoefficients, SampleAspectRatio, TransferCharacteristics};
+
+const NAL_TYPE_SPS: u8 = 7;
+const EXTENDED_SAR: u8 = 255;
+
+/// Profiles with chroma format, bit depth and scaling matrix fields in their SPS.
+const HIGH_PROFILES: [u8; 13] = [100, 110, 122, 244, 44, 83, 86, 118, 128, 138, 139, 134, 135];
+
+/// Reads bits MSB first from an RBSP, skipping emulation prevention bytes.
+struct BitReader<'a> {
+    data: &'a [u8],
+    byte: usize,
+    bit: u8,
+    zeros: usize,
+}
+
+impl<'a> BitReader<'a> {
+    const fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            byte: 0,
+            bit: 0,
+            zeros: 0,
+        }
+    }
+
+    fn bit(&mut self) -> Option<bool> {
+        if self.bit == 0 {
+            // A `3` after two zero bytes only prevents a start code from appearing in the payload.
+            if self.zeros >= 2 && *self.data.get(self.byte)? == 3 {
+                self.byte += 1;
+                self.zeros = 0;
+            }
+
+            let byte = *self.data.get(self.byte)?;
+            self.zeros = if byte == 0 { self.zeros + 1 } else { 0 };
+        }
+
+        let value = (self.data[self.byte] >> (7 - self.bit)) & 1 == 1;
+        self.bit += 1;
+
+        if self.bit == 8 {
+            self.bit = 0;
+            self.byte += 1;
+        }
+
+        Some(value)
+    }
+
+    fn bits(&mut self, n: u32) -> Option<u32> {
+        (0..n).try_fold(0, |value, _| Some((value << 1) | u32::from(self.bit()?)))
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.bits(8).map(|x| x as u8)
+    }
+
+    /// Unsigned Exp-Golomb code, `ue(v)`.
+    fn ue(&mut self) -> Option<u32> {
+        let mut leading_zeros = 0;
+
+        while !self.bit()? {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return None;
+            }
+        }
+
+        Some((1 << leading_zeros) - 1 + self.bits(leading_zeros)?)
+    }
+
+    /// Signed Exp-Golomb code, `se(v)`.
+    fn se(&mut self) -> Option<i32> {
+        let value = self.ue()?;
+        let magnitude = value.div_ceil(2) as i32;
+
+        Some(if value % 2 == 1 { magnitude } else { -magnitude })
+    }
+}
+
+/// Returns the VUI of `nal` if it is a sequence parameter set, with or without start code.
+///
+/// SPS without VUI yield the default (nothing signaled), malformed ones `None`.
+pub fn parse_vui(nal: &[u8]) -> Option<VuiInfo> {
+    let start = nal.iter().position(|&x| x != 0)?;
+    let nal = match &nal[start..] {
+        [1, rest @ ..] if start >= 2 => rest,
+        _ if start == 0 => nal,
+        _ => return None,
+    };
+
+    if nal.first()? & 0x1F != NAL_TYPE_SPS {
+        return None;
+    }
+
+    let mut reader = BitReader::new(&nal[1..]);
+    let r = &mut reader;
+
+    let profile_idc = r.byte()?;
+    let _constraint_flags = r.byte()?;
+    let _level_idc = r.byte()?;
+    let _sps_id = r.ue()?;
+
+    if HIGH_PROFILES.contains(&profile_idc) {
+        let chroma_format_idc = r.ue()?;
+        if chroma_format_idc == 3 {
+            let _separate_colour_plane = r.bit()?;
+        }
+        let _bit_depth_luma = r.ue()?;
+        let _bit_depth_chroma = r.ue()?;
+        let _qpprime_y_zero_transform_bypass = r.bit()?;
+
+        if r.bit()? {
+            let num_lists = if chroma_format_idc == 3 { 12 } else { 8 };
+            for i in 0..num_lists {
+                if r.bit()? {
+                    skip_scaling_list(r, if i < 6 { 16 } else { 64 })?;
+                }
+            }
+        }
+    }
+
+    let _log2_max_frame_num = r.ue()?;
+    match r.ue()? {
+        0 => {
+            let _log2_max_pic_order_cnt_lsb = r.ue()?;
+        }
+        1 => {
+            let _delta_pic_order_always_zero = r.bit()?;
+            let _offset_for_non_ref_pic = r.se()?;
+            let _offset_for_top_to_bottom_field = r.se()?;
+            for _ in 0..r.ue()? {
+                let _offset_for_ref_frame = r.se()?;
+            }
+        }
+        _ => {}
+    }
+
+    let _max_num_ref_frames = r.ue()?;
+    let _gaps_in_frame_num_allowed = r.bit()?;
+    let _pic_width_in_mbs = r.ue()?;
+    let _pic_height_in_map_units = r.ue()?;
+    if !r.bit()? {
+        let _mb_adaptive_frame_field = r.bit()?;
+    }
+    let _direct_8x8_inference = r.bit()?;
+    if r.bit()? {
+        for _ in 0..4 {
+            let _frame_crop_offset = r.ue()?;
+        }
+    }
+
+    if !r.bit()? {
+        return Some(VuiInfo::default());
+    }
+
+    let mut vui = VuiInfo::default();
+
+    if r.bit()? {
+        let idc = r.byte()?;
+        let (width, height) = if idc == EXTENDED_SAR {
+            (r.bits(16)? as u16, r.bits(16)? as u16)
+        } else {
+            (0, 0)
+        };
+        vui.sample_aspect_ratio = SampleAspectRatio::from_idc(idc, width, height);
+    }
+
+    if r.bit()? {
+        let _overscan_appropriate = r.bit()?;
+    }
+
+    if r.bit()? {
+        let _video_format = r.bits(3)?;
+        vui.full_range = r.bit()?;
+
+        if r.bit()? {
+            vui.color_primaries = ColorPrimaries::from_u8(r.byte()?);
+            vui.transfer_characteristics = TransferCharacteristics::from_u8(r.byte()?);
+            vui.matrix_coefficients = MatrixCoefficients::from_u8(r.byte()?);
+        }
+    }
+
+    Some(vui)
+}
+
+fn skip_scaling_list(r: &mut BitReader, size: usize) -> Option<()> {
+    let mut last_scale = 8;
+    let mut next_scale = 8;
+
+    for _ in 0..size {
+        if next_scale != 0 {
+            next_scale = (last_scale + r.se()? + 256) % 256;
+        }
+        if next_scale != 0 {
+            last_scale = next_scale;
+        }
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BitReader, parse_vui};
+    use crate::decoder::VuiInfo;
+    use crate::encoder::{ColorPrimaries, MatrixCoefficients, SampleAspectRatio, TransferCharacteristics};
+
+    /// Writes bits MSB first, inserting emulation prevention bytes.
+    #[derive(Default)]
+    struct BitWriter {
+        bits: Vec<bool>,
+    }
+
+    impl BitWriter {
+        fn bits(&mut self, value: u32, n: u32) -> &mut Self {
+            self.bits.extend((0..n).rev().map(|i| (value >> i) & 1 == 1));
+            self
+        }
+
+        fn ue(&mut self, value: u32) -> &mut Self {
+            let n = 32 - (value + 1).leading_zeros();
+            self.bits(0, n - 1).bits(value + 1, n)
+        }
+
+        fn se(&mut self, value: i32) -> &mut Self {
+            let code = if value > 0 { 2 * value - 1 } else { -2 * value };
+            self.ue(code as u32)
+        }
+
+        fn nal(&mut self) -> Vec<u8> {
+            self.bits(1, 1);
+            while self.bits.len() % 8 != 0 {
+                self.bits.push(false);
+            }
+
+            let mut nal = vec![0, 0, 0, 1];
+            let mut zeros = 0;
+            for byte in self
+                .bits
+                .chunks(8)
+                .map(|bits| bits.iter().fold(0, |acc, &b| (acc << 1) | u8::from(b)))
+            {
+                if zeros >= 2 && byte <= 3 {
+                    nal.push(3);
+                    zeros = 0;
+                }
+                zeros = if byte == 0 { zeros + 1 } else { 0 };
+                nal.push(byte);
+            }
+            nal
+        }
+    }
+
+    #[test]
+    fn exp_golomb_with_emulation_prevention() {
+        let data = BitWriter::default().ue(0).ue(1).ue(6).se(-3).bits(0, 24).ue(255).nal();
+        assert!(data[4..].windows(3).all(|w| w != [0, 0, 0]));
+
+        let mut reader = BitReader::new(&data[4..]);
+        assert_eq!(reader.ue(), Some(0));
+        assert_eq!(reader.ue(), Some(1));
+        assert_eq!(reader.ue(), Some(6));
+        assert_eq!(reader.se(), Some(-3));
+        assert_eq!(reader.bits(24), Some(0));
+        assert_eq!(reader.ue(), Some(255));
+    }
+
+    #[test]
+    fn parses_high_profile_vui() {
+        let mut sps = BitWriter::default();
+        sps.bits(0x67, 8).bits(100, 8).bits(0, 8).bits(40, 8).ue(0);
+        // 4:2:0, 8 bit, with one 4x4 and one 8x8 scaling list.
+        sps.ue(1).ue(0).ue(0).bits(0, 1).bits(1, 1);
+        sps.bits(1, 1).se(-8).bits(0, 5).bits(1, 1).se(1).se(-9).bits(0, 1);
+        // frame num, POC type 1 with two reference offsets
+        sps.ue(4).ue(1).bits(0, 1).se(-2).se(1).ue(2).se(3).se(-4);
+        // refs, gaps, 120x68 MBs, frame MBs only, direct 8x8, cropping
+        sps.ue(4)
+            .bits(0, 1)
+            .ue(119)
+            .ue(67)
+            .bits(1, 1)
+            .bits(1, 1)
+            .bits(1, 1)
+            .ue(0)
+            .ue(0)
+            .ue(0)
+            .ue(4);
+        // VUI: extended SAR, no overscan, full range BT.709
+        sps.bits(1, 1).bits(1, 1).bits(255, 8).bits(4, 16).bits(3, 16).bits(0, 1);
+        sps.bits(1, 1)
+            .bits(5, 3)
+            .bits(1, 1)
+            .bits(1, 1)
+            .bits(1, 8)
+            .bits(13, 8)
+            .bits(1, 8);
+
+        let vui = parse_vui(&sps.nal()).unwrap();
+
+        assert_eq!(vui.color_primaries, Some(ColorPrimaries::Bt709));
+        assert_eq!(vui.transfer_characteristics, Some(TransferCharacteristics::Srgb));
+        assert_eq!(vui.matrix_coefficients, Some(MatrixCoefficients::Bt709));
+        assert!(vui.full_range);
+        assert_eq!(
+            vui.sample_aspect_ratio,
+            Some(SampleAspectRatio::Extended { width: 4, height: 3 })
+        );
+    }
+
+    #[test]
+    fn parses_sps_without_vui() {
+        let mut sps = BitWriter::default();
+        sps.bits(0x67, 8).bits(66, 8).bits(0, 8).bits(30, 8).ue(0);
+        sps.ue(0)
+            .ue(2)
+            .ue(1)
+            .bits(0, 1)
+            .ue(39)
+            .ue(29)
+            .bits(1, 1)
+            .bits(1, 1)
+            .bits(0, 1)
+            .bits(0, 1);
+
+        assert_eq!(parse_vui(&sps.nal()), Some(VuiInfo::default()));
+        assert_eq!(parse_vui(&[0, 0, 1, 0x68, 0xCE, 0x3C, 0x80]), None);
+        assert_eq!(parse_vui(&[0, 0, 1, 0x67]), None);
+    }
+}
@@ -28,6 +28,11 @@ impl Timestamp {
             .try_into()
             .expect("Could not convert u64 timestamp into native timestamp")
     }
+
+    /// Converts a native timestamp, clamping negative values to [`Timestamp::ZERO`].
+    pub(crate) fn from_native(ts: c_longlong) -> Self {
+        Self(u64::try_from(ts).unwrap_or(0))
+    }
 }
 
 impl Sub for Timestamp {
@@ -66,4 +71,11 @@ mod test {
         assert_eq!((b - a).as_millis(), 100);
         assert_eq!(c.as_millis(), 200);
     }
+
+    #[test]
+    fn native_timestamps_clamp_to_zero() {
+        assert_eq!(Timestamp::from_native(100), Timestamp::from_millis(100));
+        assert_eq!(Timestamp::from_native(-1), Timestamp::ZERO);
+        assert_eq!(Timestamp::from_native(i64::MIN), Timestamp::ZERO);
+    }
 }
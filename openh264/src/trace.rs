@@ -0,0 +1,46 @@
+//! Forwards OpenH264 trace output to the `log` crate.
+//!
+//! Each encoder and decoder gets a unique instance number passed as the callback context, so
+//! messages can be told apart when several instances are running at the same time.
+
+use log::Level;
+use openh264_sys2::{WELS_LOG_DEBUG, WELS_LOG_ERROR, WELS_LOG_INFO, WELS_LOG_WARNING};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_INSTANCE: AtomicUsize = AtomicUsize::new(1);
+
+/// Returns a callback context identifying a new encoder or decoder instance.
+pub fn new_instance() -> *mut c_void {
+    std::ptr::without_provenance_mut(NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Trace callback registered with each encoder.
+pub unsafe extern "C" fn encoder_callback(ctx: *mut c_void, level: c_int, message: *const c_char) {
+    unsafe { forward("openh264::encoder", "encoder", ctx, level, message) }
+}
+
+/// Trace callback registered with each decoder.
+pub unsafe extern "C" fn decoder_callback(ctx: *mut c_void, level: c_int, message: *const c_char) {
+    unsafe { forward("openh264::decoder", "decoder", ctx, level, message) }
+}
+
+unsafe fn forward(target: &str, kind: &str, ctx: *mut c_void, level: c_int, message: *const c_char) {
+    let level = match level {
+        WELS_LOG_ERROR => Level::Error,
+        WELS_LOG_WARNING => Level::Warn,
+        WELS_LOG_INFO => Level::Info,
+        WELS_LOG_DEBUG => Level::Debug,
+        _ => Level::Trace,
+    };
+
+    if message.is_null() || !log::log_enabled!(target: target, level) {
+        return;
+    }
+
+    // SAFETY: OpenH264 passes a NUL-terminated string that is valid for the duration of the call.
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+
+    log::log!(target: target, level, "{kind} #{}: {}", ctx.addr(), message.trim_end());
+}
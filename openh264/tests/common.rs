@@ -0,0 +1,48 @@
+use openh264::Error;
+use openh264::decoder::{DecodedYUV, Decoder};
+use openh264::encoder::Encoder;
+use openh264::formats::{RgbSliceU8, YUVBuffer};
+
+/// Creates a gradient frame, so tests don't depend on any image data.
+#[allow(dead_code)]
+pub fn gradient_yuv(width: usize, height: usize) -> YUVBuffer {
+    moving_gradient_yuv(width, height, 0)
+}
+
+/// Creates frame `i` of a gradient moving to the right, so consecutive frames differ.
+#[allow(dead_code)]
+pub fn moving_gradient_yuv(width: usize, height: usize, i: usize) -> YUVBuffer {
+    let rgb = (0..width * height)
+        .flat_map(|x| {
+            [
+                ((x + i * 3) % width * 255 / width) as u8,
+                (x / width * 255 / height) as u8,
+                128,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    YUVBuffer::from_rgb_source(RgbSliceU8::new(&rgb, (width, height)))
+}
+
+/// Encodes `count` frames of a moving gradient, returning one packet per frame.
+///
+/// # Errors
+///
+/// Fails if any frame fails to encode.
+#[allow(dead_code)]
+pub fn encode_gradient(encoder: &mut Encoder, dimensions: (usize, usize), count: usize) -> Result<Vec<Vec<u8>>, Error> {
+    (0..count)
+        .map(|i| Ok(encoder.encode(&moving_gradient_yuv(dimensions.0, dimensions.1, i))?.to_vec()))
+        .collect()
+}
+
+/// Decodes a packet that must produce a frame.
+///
+/// # Errors
+///
+/// Fails if decoding fails or the decoder didn't return a frame.
+#[allow(dead_code)]
+pub fn decode_frame<'a>(decoder: &'a mut Decoder, packet: &[u8]) -> Result<DecodedYUV<'a>, Error> {
+    decoder.decode(packet)?.ok_or_else(|| Error::msg("Must have image"))
+}
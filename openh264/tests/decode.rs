@@ -1,10 +1,17 @@
 use std::io::{Cursor, Read};
 
+use common::{decode_frame, encode_gradient, gradient_yuv, moving_gradient_yuv};
 use image::RgbImage;
-use openh264::decoder::{Decoder, DecoderConfig, Flush};
-use openh264::formats::{RgbSliceU8, YUVSource};
+use openh264::decoder::{Decoder, DecoderConfig, ErrorConcealment, Flush, FrameInfo, FramePool, VuiInfo};
+use openh264::encoder::{
+    ColorPrimaries, Encoder, EncoderConfig, EntropyCoding, LtrMarking, MatrixCoefficients, Profile, SampleAspectRatio,
+    TransferCharacteristics, VuiConfig,
+};
+use openh264::formats::{ColorSpace, RGB8Layout, RgbSliceU8, YUVSource};
 use openh264::{Error, OpenH264API, nal_units};
 
+mod common;
+
 #[test]
 #[cfg(feature = "source")]
 fn can_get_decoder() -> Result<(), Error> {
@@ -231,6 +238,294 @@ fn decodes_file_with_bframes() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+#[cfg(feature = "source")]
+fn decoded_write_par_matches_single_threaded() -> Result<(), Error> {
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+    let packet = encoder.encode(&gradient_yuv(64, 48))?.to_vec();
+
+    let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::default())?;
+    let frame = decode_frame(&mut decoder, &packet)?;
+
+    let mut rgb = vec![0; frame.rgb8_len()];
+    let mut rgba = vec![0; frame.rgba8_len()];
+    frame.write_rgb8_with_color_space(&mut rgb, ColorSpace::bt709());
+    frame.write_rgba8_with_color_space(&mut rgba, ColorSpace::bt709());
+
+    for num_threads in [1, 3, 8] {
+        let mut target = vec![0; rgb.len()];
+        frame.write_rgb8_par(&mut target, ColorSpace::bt709(), num_threads);
+        assert_eq!(target, rgb);
+
+        let mut target = vec![0; rgba.len()];
+        frame.write_rgba8_par(&mut target, ColorSpace::bt709(), num_threads);
+        assert_eq!(target, rgba);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn decoded_write_rgb8_layouts() -> Result<(), Error> {
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+    let packet = encoder.encode(&gradient_yuv(64, 48))?.to_vec();
+
+    let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::default())?;
+    let frame = decode_frame(&mut decoder, &packet)?;
+
+    let mut rgba = vec![0; frame.rgba8_len()];
+    frame.write_rgba8(&mut rgba);
+
+    let mut bgra = vec![0; frame.rgba8_len()];
+    frame.write_rgb8_layout(&mut bgra, RGB8Layout::Bgra, ColorSpace::default());
+    let swizzled = rgba
+        .chunks_exact(4)
+        .flat_map(|x| [x[2], x[1], x[0], x[3]])
+        .collect::<Vec<_>>();
+    assert_eq!(bgra, swizzled);
+
+    let mut bgr = vec![0; frame.rgb8_len()];
+    frame.write_rgb8_layout(&mut bgr, RGB8Layout::Bgr, ColorSpace::default());
+    let swizzled = rgba.chunks_exact(4).flat_map(|x| [x[2], x[1], x[0]]).collect::<Vec<_>>();
+    assert_eq!(bgr, swizzled);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn owned_frames_outlive_decode() -> Result<(), Error> {
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+    let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::default())?;
+    let pool = FramePool::new();
+
+    let mut frames = Vec::new();
+    let mut expected = Vec::new();
+
+    for packet in encode_gradient(&mut encoder, (64, 48), 3)? {
+        let frame = decode_frame(&mut decoder, &packet)?;
+
+        let mut rgb = vec![0; frame.rgb8_len()];
+        frame.write_rgb8(&mut rgb);
+        expected.push((frame.timestamp(), rgb));
+        frames.push(frame.to_owned_frame_with_pool(&pool));
+    }
+
+    // Frames are still valid after the decoder moved on, and can be converted on another thread.
+    let converted = std::thread::spawn(move || {
+        frames
+            .iter()
+            .map(|frame| {
+                let mut rgb = vec![0; frame.rgb8_len()];
+                frame.write_rgb8_layout(&mut rgb, RGB8Layout::Rgb, ColorSpace::default());
+                (frame.timestamp(), rgb)
+            })
+            .collect::<Vec<_>>()
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(converted, expected);
+    assert_ne!(expected[0], expected[1]);
+    assert_eq!(pool.available(), 3);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn error_concealment_returns_damaged_frames() -> Result<(), Error> {
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+    let mut packets = encode_gradient(&mut encoder, (64, 48), 5)?;
+
+    // Lose the second P frame.
+    packets.remove(2);
+
+    let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::new())?;
+    assert!(decoder.decode(&packets[0])?.is_some());
+    assert!(decoder.decode(&packets[1])?.is_some());
+    assert!(decoder.decode(&packets[2]).is_err());
+
+    for concealment in [ErrorConcealment::FrameCopy, ErrorConcealment::SliceMvCopyCrossIdr] {
+        let config = DecoderConfig::new().error_concealment(concealment);
+        let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), config)?;
+
+        let concealed = packets
+            .iter()
+            .map(|packet| Ok(decoder.decode(packet)?.map(|yuv| yuv.to_owned_frame().is_concealed())))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        assert_eq!(concealed, [Some(false), Some(false), Some(true), Some(true)], "{concealment:?}");
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn decoder_statistics() -> Result<(), Error> {
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+    let mut packets = encode_gradient(&mut encoder, (64, 48), 5)?;
+
+    // Lose the second P frame.
+    packets.remove(2);
+
+    let config = DecoderConfig::new()
+        .error_concealment(ErrorConcealment::FrameCopy)
+        .statistics_log_interval(2);
+    let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), config)?;
+    assert_eq!(decoder.statistics()?.decoded_frame_count, 0);
+    assert_eq!(decoder.statistics()?.average_luma_qp, None);
+
+    for packet in &packets {
+        decoder.decode(packet)?;
+    }
+
+    let stats = decoder.statistics()?;
+    assert_eq!((stats.width, stats.height), (64, 48));
+    assert_eq!(stats.decoded_frame_count, 4);
+    assert_eq!(stats.idr_count, 1);
+    assert_eq!(stats.concealed_frame_count, 2);
+    assert_eq!(stats.lost_idr_count, 0);
+    assert_eq!(stats.profile_idc, 66);
+    assert!(stats.average_luma_qp.is_some());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn decoded_frames_carry_frame_info() -> Result<(), Error> {
+    // Every other frame of the upper temporal layer is not referenced, and doesn't advance `frame_num`.
+    let config = EncoderConfig::new().temporal_layers(2);
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+    let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::new())?;
+
+    let mut infos = Vec::new();
+    for packet in encode_gradient(&mut encoder, (64, 48), 4)? {
+        let yuv = decode_frame(&mut decoder, &packet)?;
+        assert_eq!(yuv.to_owned_frame().frame_info(), yuv.frame_info());
+        infos.push(yuv.frame_info());
+    }
+
+    assert_eq!(
+        infos.iter().map(|x| x.frame_num).collect::<Vec<_>>(),
+        [Some(0), Some(1), Some(1), Some(2)]
+    );
+    assert_eq!(
+        infos.iter().map(|x| x.is_reference).collect::<Vec<_>>(),
+        [true, false, true, false]
+    );
+    assert!(infos.iter().all(|x| x.idr_pic_id.is_some() && x.temporal_id == Some(0)));
+
+    // Report marked LTR frames back to the encoder, which then keeps marking new ones.
+    let config = EncoderConfig::new().long_term_reference(true).ltr_mark_period(5);
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+    let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::new())?;
+
+    let mut marked = Vec::new();
+    for i in 0..16 {
+        let packet = encoder.encode(&moving_gradient_yuv(64, 48, i))?.to_vec();
+        let info = decode_frame(&mut decoder, &packet)?.frame_info();
+        let idr_pic_id = info.idr_pic_id.ok_or_else(|| Error::msg("Must have IDR id"))?;
+
+        // The IDR frame is a long term reference without explicit marking.
+        let ltr_frame_num = if i == 0 { Some(0) } else { info.ltr_marked_frame_num };
+        if let Some(ltr_frame_num) = ltr_frame_num {
+            encoder.ltr_marking_feedback(0, idr_pic_id, ltr_frame_num, LtrMarking::Success)?;
+        }
+
+        marked.extend(info.ltr_marked_frame_num);
+    }
+
+    assert!(!marked.is_empty());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn flushed_frames_have_no_frame_info() -> Result<(), Error> {
+    // Unlike baseline streams, the decoder holds back high profile frames until it knows their display order.
+    let config = EncoderConfig::new()
+        .profile(Profile::High)
+        .entropy_coding(EntropyCoding::Cabac);
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+    let packets = encode_gradient(&mut encoder, (64, 48), 4)?;
+
+    // Flushing after each packet returns every frame right away, along with its own info.
+    let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::new())?;
+    let mut frame_nums = Vec::new();
+    for packet in &packets {
+        frame_nums.push(decode_frame(&mut decoder, packet)?.frame_info().frame_num);
+    }
+    assert_eq!(frame_nums, [Some(0), Some(1), Some(2), Some(3)]);
+
+    // Otherwise each frame is returned while decoding the next one, and the last one by `flush_remaining()`.
+    let config = DecoderConfig::new().flush_after_decode(Flush::NoFlush);
+    let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), config)?;
+    let mut infos = Vec::new();
+    for packet in &packets {
+        infos.push(decoder.decode(packet)?.map(|yuv| yuv.frame_info()));
+    }
+    infos.extend(decoder.flush_remaining()?.iter().map(|yuv| Some(yuv.frame_info())));
+
+    assert_eq!(infos[0], None);
+    assert_eq!(infos[1..], [Some(FrameInfo::default()); 4]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn decoder_reports_vui() -> Result<(), Error> {
+    for (vui, expected) in [
+        (None, VuiInfo::default()),
+        (
+            Some(VuiConfig::srgb().sample_aspect_ratio(SampleAspectRatio::Extended { width: 4, height: 3 })),
+            VuiInfo {
+                color_primaries: Some(ColorPrimaries::Bt709),
+                transfer_characteristics: Some(TransferCharacteristics::Srgb),
+                matrix_coefficients: Some(MatrixCoefficients::Bt709),
+                full_range: true,
+                sample_aspect_ratio: Some(SampleAspectRatio::Extended { width: 4, height: 3 }),
+            },
+        ),
+        (
+            Some(VuiConfig::bt2020().sample_aspect_ratio(SampleAspectRatio::Sar16x11)),
+            VuiInfo {
+                color_primaries: Some(ColorPrimaries::Bt2020),
+                transfer_characteristics: Some(TransferCharacteristics::Bt2020_10),
+                matrix_coefficients: Some(MatrixCoefficients::Bt2020Ncl),
+                full_range: false,
+                sample_aspect_ratio: Some(SampleAspectRatio::Sar16x11),
+            },
+        ),
+    ] {
+        let config = vui.map_or_else(EncoderConfig::new, |vui| EncoderConfig::new().vui(vui));
+        let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+        let packet = encoder.encode(&gradient_yuv(64, 48))?.to_vec();
+
+        let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::default())?;
+        let frame = decode_frame(&mut decoder, &packet)?;
+
+        assert_eq!(frame.vui(), expected);
+        assert_eq!(
+            frame.vui().color_space(),
+            vui.map_or_else(ColorSpace::default, |vui| vui.color_space())
+        );
+
+        let mut auto = vec![0; frame.rgb8_len()];
+        let mut manual = vec![0; frame.rgb8_len()];
+        frame.write_rgb8_auto(&mut auto);
+        frame.write_rgb8_with_color_space(&mut manual, frame.vui().color_space());
+        assert_eq!(auto, manual);
+    }
+
+    Ok(())
+}
+
 // TODO: Can we remove this to use `to_bitstream_with_001_le` above?
 // The packets in the file are written frame by frame
 // the first 4 bytes are frame length in little endian
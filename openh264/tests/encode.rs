@@ -1,10 +1,17 @@
 #![allow(clippy::bool_assert_comparison)]
 
+use common::{decode_frame, gradient_yuv, moving_gradient_yuv};
 use openh264::decoder::{Decoder, DecoderConfig};
-use openh264::encoder::{Encoder, EncoderConfig, FrameType};
-use openh264::formats::{RgbSliceU8, YUVBuffer, YUVSource};
+use openh264::encoder::{
+    BitRate, Deblocking, DeblockingMode, EncodedFrame, Encoder, EncoderConfig, EntropyCoding, FrameRate, FrameType, Layer,
+    LayerType, LtrMarking, NalUnitType, Profile, SampleAspectRatio, SliceMode, SpatialLayer, UsageType, VuiConfig,
+};
+use openh264::formats::{ColorSpace, NV12Slices, RgbSliceU8, YUVBuffer, YUVSource, YuyvSliceU8};
 use openh264::{Error, OpenH264API, Timestamp};
 use openh264_sys2::DynamicAPI;
+use std::time::Duration;
+
+mod common;
 
 #[test]
 #[cfg(feature = "source")]
@@ -59,7 +66,6 @@ fn encode() -> Result<(), Error> {
 
 #[test]
 #[ignore = "Timestamp logic broken atm"]
-#[allow(clippy::similar_names)]
 #[cfg(feature = "source")]
 fn encode_at_timestamp_roundtrips() -> Result<(), Error> {
     let src = include_bytes!("data/lenna_128x128.rgb");
@@ -71,13 +77,13 @@ fn encode_at_timestamp_roundtrips() -> Result<(), Error> {
     let mut encoder = Encoder::with_api_config(api, config)?;
 
     let timestamp = Timestamp::from_millis(64);
-    let encoded = encoder.encode_at(&yuv, timestamp)?.to_vec();
+    let packet = encoder.encode_at(&yuv, timestamp)?.to_vec();
 
     let api = OpenH264API::from_source();
     let config = DecoderConfig::default();
     let mut decoder = Decoder::with_api_config(api, config)?;
     let yuv = decoder
-        .decode(encoded.as_slice())?
+        .decode(packet.as_slice())?
         .ok_or_else(|| Error::msg("Must have image"))?;
 
     assert_eq!(yuv.dimensions().0, 128);
@@ -89,7 +95,6 @@ fn encode_at_timestamp_roundtrips() -> Result<(), Error> {
 
 #[test]
 #[cfg(feature = "source")]
-#[allow(clippy::similar_names)]
 fn encoder_sps_pps() -> Result<(), Error> {
     let src = include_bytes!("data/lenna_128x128.rgb");
     let rgb_source = RgbSliceU8::new(src, (128, 128));
@@ -102,11 +107,11 @@ fn encoder_sps_pps() -> Result<(), Error> {
     let stream = encoder.encode(&yuv)?;
 
     let layer_0 = stream.layer(0).unwrap();
-    let raw_sps = layer_0.nal_unit(0).unwrap();
-    let raw_pps = layer_0.nal_unit(1).unwrap();
+    let sps = layer_0.nal_unit(0).unwrap();
+    let pps = layer_0.nal_unit(1).unwrap();
 
-    assert!(!raw_sps.is_empty());
-    assert!(!raw_pps.is_empty());
+    assert!(!sps.is_empty());
+    assert!(!pps.is_empty());
 
     Ok(())
 }
@@ -193,3 +198,569 @@ fn encode_change_resolution() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_simulcast_spatial_layers() -> Result<(), Error> {
+    let yuv = gradient_yuv(640, 360);
+
+    let api = OpenH264API::from_source();
+    let config = EncoderConfig::new()
+        .spatial_layer(SpatialLayer::new(320, 180, BitRate::from_bps(200_000)))
+        .spatial_layer(SpatialLayer::new(640, 360, BitRate::from_bps(800_000)))
+        .simulcast(true);
+    let mut encoder = Encoder::with_api_config(api, config)?;
+
+    let stream = encoder.encode(&yuv)?;
+
+    assert_eq!(stream.frame_type(), FrameType::IDR);
+
+    for (spatial_id, dimensions) in [(0, (320, 180)), (1, (640, 360))] {
+        let mut packet = Vec::new();
+        stream.write_spatial_layer_vec(spatial_id, &mut packet);
+
+        let api = OpenH264API::from_source();
+        let mut decoder = Decoder::with_api_config(api, DecoderConfig::default())?;
+        let frame = decode_frame(&mut decoder, &packet)?;
+
+        assert_eq!(frame.dimensions(), dimensions);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_spatial_layers_must_ascend() -> Result<(), Error> {
+    let yuv = gradient_yuv(640, 360);
+
+    let api = OpenH264API::from_source();
+    let config = EncoderConfig::new()
+        .spatial_layer(SpatialLayer::new(640, 360, BitRate::from_bps(800_000)))
+        .spatial_layer(SpatialLayer::new(320, 180, BitRate::from_bps(200_000)));
+    let mut encoder = Encoder::with_api_config(api, config)?;
+
+    assert!(encoder.encode(&yuv).is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_temporal_layers() -> Result<(), Error> {
+    let yuv = gradient_yuv(128, 128);
+
+    let api = OpenH264API::from_source();
+    let config = EncoderConfig::new().temporal_layers(3);
+    let mut encoder = Encoder::with_api_config(api, config)?;
+
+    let mut temporal_ids = Vec::new();
+
+    for _ in 0..8 {
+        let stream = encoder.encode(&yuv)?;
+
+        for i in 0..stream.num_layers() {
+            let layer = stream.layer(i).unwrap();
+
+            if layer.layer_type() == LayerType::Video {
+                temporal_ids.push(layer.temporal_id());
+            }
+        }
+    }
+
+    assert_eq!(temporal_ids, [0, 2, 1, 2, 0, 2, 1, 2]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_change_bitrate_and_frame_rate() -> Result<(), Error> {
+    use openh264_sys2::{ENCODER_OPTION_BITRATE, ENCODER_OPTION_FRAME_RATE, SBitrateInfo, SPATIAL_LAYER_ALL};
+
+    let api = OpenH264API::from_source();
+    let config = EncoderConfig::new().max_frame_rate(FrameRate::from_hz(30.0));
+    let mut encoder = Encoder::with_api_config(api, config)?;
+
+    let native_values = |encoder: &mut Encoder| unsafe {
+        let mut bitrate = SBitrateInfo {
+            iLayer: SPATIAL_LAYER_ALL,
+            iBitrate: 0,
+        };
+        let mut frame_rate = 0.0f32;
+        encoder
+            .raw_api()
+            .get_option(ENCODER_OPTION_BITRATE, (&raw mut bitrate).cast());
+        encoder
+            .raw_api()
+            .get_option(ENCODER_OPTION_FRAME_RATE, (&raw mut frame_rate).cast());
+        (bitrate.iBitrate, frame_rate)
+    };
+
+    assert_eq!(encoder.encode(&gradient_yuv(128, 128))?.frame_type(), FrameType::IDR);
+
+    encoder.set_bitrate(BitRate::from_bps(500_000))?;
+    encoder.set_max_bitrate(BitRate::from_bps(800_000))?;
+    encoder.set_frame_rate(FrameRate::from_hz(15.0))?;
+
+    assert_ne!(encoder.encode(&gradient_yuv(128, 128))?.frame_type(), FrameType::IDR);
+    assert_eq!(native_values(&mut encoder), (500_000, 15.0));
+
+    // Values must survive a re-initialization caused by a resolution change.
+    assert_eq!(encoder.encode(&gradient_yuv(256, 256))?.frame_type(), FrameType::IDR);
+    assert_eq!(native_values(&mut encoder), (500_000, 15.0));
+
+    encoder.set_bitrate(BitRate::from_bps(200_000))?;
+    encoder.encode(&gradient_yuv(256, 256))?;
+    assert_eq!(native_values(&mut encoder).0, 200_000);
+
+    assert!(encoder.set_bitrate(BitRate::from_bps(0)).is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_change_bitrate_of_spatial_layers() -> Result<(), Error> {
+    use openh264_sys2::{ENCODER_OPTION_BITRATE, SBitrateInfo, SPATIAL_LAYER_0, SPATIAL_LAYER_1};
+
+    let layer_bitrates = |encoder: &mut Encoder| {
+        [SPATIAL_LAYER_0, SPATIAL_LAYER_1].map(|layer| unsafe {
+            let mut bitrate = SBitrateInfo {
+                iLayer: layer,
+                iBitrate: 0,
+            };
+            encoder
+                .raw_api()
+                .get_option(ENCODER_OPTION_BITRATE, (&raw mut bitrate).cast());
+            bitrate.iBitrate
+        })
+    };
+
+    let config = EncoderConfig::new()
+        .spatial_layer(SpatialLayer::new(320, 180, BitRate::from_bps(200_000)))
+        .spatial_layer(SpatialLayer::new(640, 360, BitRate::from_bps(800_000)))
+        .simulcast(true);
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+    encoder.encode(&gradient_yuv(640, 360))?;
+
+    encoder.set_bitrate(BitRate::from_bps(3_000_001))?;
+    assert_eq!(layer_bitrates(&mut encoder), [600_000, 2_400_000]);
+
+    // A layer exceeding its max bit rate must be rejected before any layer is changed.
+    let config = EncoderConfig::new()
+        .spatial_layer(SpatialLayer::new(320, 180, BitRate::from_bps(200_000)).max_bitrate(BitRate::from_bps(300_000)))
+        .spatial_layer(SpatialLayer::new(640, 360, BitRate::from_bps(800_000)))
+        .simulcast(true);
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+    encoder.encode(&gradient_yuv(640, 360))?;
+
+    assert!(encoder.set_bitrate(BitRate::from_bps(3_000_000)).is_err());
+    assert_eq!(layer_bitrates(&mut encoder), [200_000, 800_000]);
+
+    encoder.set_bitrate(BitRate::from_bps(1_250_000))?;
+    assert_eq!(layer_bitrates(&mut encoder), [250_000, 1_000_000]);
+
+    // Layers without bit rate share the new one evenly.
+    let config = EncoderConfig::new()
+        .spatial_layer(SpatialLayer::new(320, 180, BitRate::from_bps(0)))
+        .spatial_layer(SpatialLayer::new(640, 360, BitRate::from_bps(0)))
+        .simulcast(true);
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+
+    encoder.set_bitrate(BitRate::from_bps(1_000_000))?;
+    encoder.encode(&gradient_yuv(640, 360))?;
+    assert_eq!(layer_bitrates(&mut encoder), [500_000, 500_000]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encoder_statistics() -> Result<(), Error> {
+    let api = OpenH264API::from_source();
+    let config = EncoderConfig::new().statistics_log_interval(Duration::from_secs(1));
+    let mut encoder = Encoder::with_api_config(api, config)?;
+
+    assert!(encoder.statistics().is_err());
+
+    for i in 0..10 {
+        encoder.encode_at(&gradient_yuv(128, 96), Timestamp::from_millis(i * 33))?;
+    }
+
+    let stats = encoder.statistics()?;
+
+    assert_eq!((stats.width, stats.height), (128, 96));
+    assert_eq!(stats.input_frame_count, 10);
+    assert_eq!(stats.idr_sent_count, 1);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_slice_modes() -> Result<(), Error> {
+    // 320x192 is 20x12 macroblocks.
+    let yuv = gradient_yuv(320, 192);
+
+    for (mode, slices) in [
+        (SliceMode::Single, 1),
+        (SliceMode::Fixed(4), 4),
+        (SliceMode::Raster(0), 12),
+        (SliceMode::Raster(100), 3),
+    ] {
+        let config = EncoderConfig::new().slice_mode(mode);
+        let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+        let stream = encoder.encode(&yuv)?;
+        let video = (0..stream.num_layers())
+            .filter_map(|i| stream.layer(i))
+            .find(Layer::is_video)
+            .unwrap();
+
+        assert_eq!(video.nal_count(), slices, "{mode:?}");
+    }
+
+    // At most 35 slices per frame, 576 pixels are 36 macroblock rows.
+    for (mode, (width, height)) in [
+        (SliceMode::Fixed(36), (320, 192)),
+        (SliceMode::Raster(6), (320, 192)),
+        (SliceMode::Raster(0), (64, 576)),
+    ] {
+        let config = EncoderConfig::new().slice_mode(mode);
+        let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+
+        assert!(encoder.encode(&gradient_yuv(width, height)).is_err(), "{mode:?}");
+    }
+
+    let config = EncoderConfig::new().slice_mode(SliceMode::Fixed(0));
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+    encoder.encode(&yuv)?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_deblocking_and_entropy_coding() -> Result<(), Error> {
+    let yuv = gradient_yuv(128, 96);
+
+    for (deblocking, entropy_coding, profile_idc) in [
+        (Deblocking::new(DeblockingMode::Off), EntropyCoding::Cavlc, 66),
+        (
+            Deblocking::new(DeblockingMode::OffAcrossSlices).offsets(-6, 6),
+            EntropyCoding::Cavlc,
+            66,
+        ),
+        (Deblocking::new(DeblockingMode::On).offsets(3, -2), EntropyCoding::Cabac, 100),
+    ] {
+        let config = EncoderConfig::new().deblocking(deblocking).entropy_coding(entropy_coding);
+        let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+        let packet = encoder.encode(&yuv)?.to_vec();
+
+        // SPS follows the 4 byte start code and the NAL header.
+        assert_eq!(packet[5], profile_idc);
+
+        let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::default())?;
+        let frame = decode_frame(&mut decoder, &packet)?;
+
+        assert_eq!(frame.dimensions(), (128, 96));
+    }
+
+    let config = EncoderConfig::new()
+        .entropy_coding(EntropyCoding::Cabac)
+        .profile(Profile::Baseline);
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+
+    assert!(encoder.encode(&yuv).is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_sample_aspect_ratio() -> Result<(), Error> {
+    use openh264_sys2::{DECODER_OPTION_GET_SAR_INFO, SVuiSarInfo};
+
+    let yuv = gradient_yuv(144, 108);
+
+    for (sar, expected) in [
+        (None, (0, 0)),
+        (Some(SampleAspectRatio::Sar16x11), (16, 11)),
+        (Some(SampleAspectRatio::Extended { width: 4, height: 3 }), (4, 3)),
+    ] {
+        let vui = sar.map_or_else(VuiConfig::new, |sar| VuiConfig::new().sample_aspect_ratio(sar));
+        let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new().vui(vui))?;
+        let packet = encoder.encode(&yuv)?.to_vec();
+
+        let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::default())?;
+        decoder.decode(&packet)?;
+
+        let mut info = SVuiSarInfo::default();
+        unsafe {
+            decoder
+                .raw_api()
+                .get_option(DECODER_OPTION_GET_SAR_INFO, (&raw mut info).cast());
+        }
+
+        assert_eq!((info.uiSarWidth, info.uiSarHeight), expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_odd_and_unaligned_sizes() -> Result<(), Error> {
+    for (dimensions, expected) in [((130, 98), (130, 98)), ((99, 67), (100, 68)), ((1366, 767), (1366, 768))] {
+        let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+        let packet = encoder.encode(&gradient_yuv(dimensions.0, dimensions.1))?.to_vec();
+
+        let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::default())?;
+        let frame = decode_frame(&mut decoder, &packet)?;
+
+        assert_eq!(frame.dimensions(), expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_nv12_matches_i420() -> Result<(), Error> {
+    // Odd sizes are padded while splitting the chroma plane.
+    for (w, h) in [(64, 48), (63, 47)] {
+        let yuv = gradient_yuv(w, h);
+        let uv = yuv.u().iter().zip(yuv.v()).flat_map(|(u, v)| [*u, *v]).collect::<Vec<_>>();
+        let vu = yuv.u().iter().zip(yuv.v()).flat_map(|(u, v)| [*v, *u]).collect::<Vec<_>>();
+        let uv_stride = 2 * w.div_ceil(2);
+
+        let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+        let expected = encoder.encode(&yuv)?.to_vec();
+
+        for nv12 in [
+            NV12Slices::new(yuv.y(), &uv, (w, h), (w, uv_stride)),
+            NV12Slices::new_nv21(yuv.y(), &vu, (w, h), (w, uv_stride)),
+        ] {
+            let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+
+            assert_eq!(encoder.encode(&nv12)?.to_vec(), expected);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_yuyv_matches_i420() -> Result<(), Error> {
+    let yuv = gradient_yuv(64, 48);
+
+    // Repeat each chroma row, so decimating back to 4:2:0 is lossless.
+    let packed = (0..48)
+        .flat_map(|row| {
+            let (y, u, v) = (
+                &yuv.y()[row * 64..][..64],
+                &yuv.u()[row / 2 * 32..][..32],
+                &yuv.v()[row / 2 * 32..][..32],
+            );
+            (0..32).flat_map(move |i| [y[2 * i], u[i], y[2 * i + 1], v[i]])
+        })
+        .collect::<Vec<_>>();
+
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+    let expected = encoder.encode(&yuv)?.to_vec();
+
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+    assert_eq!(encoder.encode(&YuyvSliceU8::new(&packed, (64, 48)))?.to_vec(), expected);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_decode_with_color_space() -> Result<(), Error> {
+    let (width, height) = (64, 48);
+    let rgb = (0..width * height)
+        .flat_map(|i| [(i % width * 255 / width) as u8, (i / width * 255 / height) as u8, 200])
+        .collect::<Vec<_>>();
+    let mean_error = |a: &[u8], b: &[u8]| a.iter().zip(b).map(|(a, b)| f64::from(a.abs_diff(*b))).sum::<f64>() / a.len() as f64;
+
+    for vui in [VuiConfig::bt709(), VuiConfig::bt709_full(), VuiConfig::bt2020()] {
+        let mut yuv = YUVBuffer::new(width, height);
+        yuv.read_rgb8_with_color_space(RgbSliceU8::new(&rgb, (width, height)), vui.color_space());
+
+        let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new().vui(vui))?;
+        let packet = encoder.encode(&yuv)?.to_vec();
+
+        let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::default())?;
+        let frame = decode_frame(&mut decoder, &packet)?;
+
+        let mut matching = vec![0; rgb.len()];
+        let mut default = vec![0; rgb.len()];
+        frame.write_rgb8_with_color_space(&mut matching, vui.color_space());
+        frame.write_rgb8_with_color_space(&mut default, ColorSpace::default());
+
+        // Converting back with BT.601 shifts the colors noticeably.
+        assert!(mean_error(&rgb, &matching) < 3.0);
+        assert!(mean_error(&rgb, &default) > 2.0 * mean_error(&rgb, &matching));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encoded_frames_keep_metadata() -> Result<(), Error> {
+    let yuv = gradient_yuv(64, 48);
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+
+    let frames = (0..2)
+        .map(|i| Ok(encoder.encode_at(&yuv, Timestamp::from_millis(i * 40))?.to_frame()))
+        .collect::<Result<Vec<EncodedFrame>, Error>>()?;
+
+    let types = |frame: &EncodedFrame| frame.nal_units().iter().map(|x| x.nal_type).collect::<Vec<_>>();
+    assert_eq!(types(&frames[0]), [NalUnitType::Sps, NalUnitType::Pps, NalUnitType::SliceIdr]);
+    assert_eq!(types(&frames[1]), [NalUnitType::Slice]);
+
+    assert!(frames[0].is_idr());
+    assert_eq!(frames[1].frame_type(), FrameType::P);
+    assert_eq!(frames[1].timestamp(), Timestamp::from_millis(40));
+
+    for frame in &frames {
+        // NAL units are contiguous, and each starts with a start code.
+        let mut offset = 0;
+        for (i, nal) in frame.nal_units().iter().enumerate() {
+            assert_eq!(nal.offset, offset);
+            assert!(frame.nal_unit(i).unwrap().starts_with(&[0, 0, 0, 1]));
+            offset += nal.len;
+        }
+        assert_eq!(offset, frame.len());
+    }
+
+    // Frames can be decoded after the encoder moved on.
+    let mut decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::default())?;
+    for frame in frames {
+        assert!(decoder.decode(frame.data())?.is_some());
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_psnr() -> Result<(), Error> {
+    let yuv = gradient_yuv(128, 96);
+
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new().psnr(true))?;
+    let stream = encoder.encode(&yuv)?;
+    let layers = (0..stream.num_layers()).filter_map(|i| stream.layer(i)).collect::<Vec<_>>();
+
+    assert!(layers.iter().filter(|l| !l.is_video()).all(|l| l.psnr().is_none()));
+
+    let psnr = layers.iter().find_map(Layer::psnr).unwrap();
+    assert!(psnr.y > 20.0 && psnr.u > 20.0 && psnr.v > 20.0);
+
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+    let stream = encoder.encode(&yuv)?;
+
+    assert!(
+        (0..stream.num_layers())
+            .filter_map(|i| stream.layer(i))
+            .all(|l| l.psnr().is_none())
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_ltr_feedback() -> Result<(), Error> {
+    let api = OpenH264API::from_source();
+    let config = EncoderConfig::new().long_term_reference(true).ltr_mark_period(5);
+    let mut encoder = Encoder::with_api_config(api, config)?;
+    let yuv = gradient_yuv(128, 128);
+
+    assert!(encoder.ltr_recovery_request(0, 0, None, None).is_err());
+    assert!(encoder.ltr_marking_feedback(0, 1, 0, LtrMarking::Success).is_err());
+
+    assert_eq!(encoder.encode(&yuv)?.frame_type(), FrameType::IDR);
+    assert_eq!(encoder.encode(&yuv)?.frame_type(), FrameType::P);
+
+    // OpenH264 writes the first IDR frame with `idr_pic_id` 1.
+    encoder.ltr_marking_feedback(0, 1, 0, LtrMarking::Success)?;
+    assert_eq!(encoder.encode(&yuv)?.frame_type(), FrameType::P);
+
+    // Without a correctly decoded frame, the encoder has to start over.
+    encoder.ltr_recovery_request(0, 1, None, None)?;
+    assert_eq!(encoder.encode(&yuv)?.frame_type(), FrameType::IDR);
+
+    // Marking feedback is meaningless without long term references.
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), EncoderConfig::new())?;
+    encoder.encode(&yuv)?;
+    assert!(encoder.ltr_marking_feedback(0, 1, 0, LtrMarking::Success).is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_ltr_recovery_from_marked_frame() -> Result<(), Error> {
+    let config = EncoderConfig::new().long_term_reference(true).ltr_mark_period(5);
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+    let mut decoder = Decoder::new()?;
+    let mut packets = Vec::new();
+
+    for i in 0..5 {
+        packets.push(encoder.encode(&moving_gradient_yuv(128, 128, i))?.to_vec());
+
+        if i == 1 {
+            encoder.ltr_marking_feedback(0, 1, 0, LtrMarking::Success)?;
+        }
+    }
+
+    // Frames 3 and 4 got lost, frame 2 was the last one decoded correctly.
+    encoder.ltr_recovery_request(0, 1, Some(2), Some(4))?;
+    let recovery = encoder.encode(&moving_gradient_yuv(128, 128, 5))?;
+    assert_eq!(recovery.frame_type(), FrameType::P);
+
+    for packet in &packets[..3] {
+        decode_frame(&mut decoder, packet)?;
+    }
+
+    decode_frame(&mut decoder, &recovery.to_vec())?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "source")]
+fn encode_ltr_ref_num_limit() -> Result<(), Error> {
+    let yuv = gradient_yuv(64, 64);
+    let config = EncoderConfig::new().long_term_reference(true).ltr_ref_num(2);
+    Encoder::with_api_config(OpenH264API::from_source(), config)?.encode(&yuv)?;
+
+    let config = EncoderConfig::new().long_term_reference(true).ltr_ref_num(3);
+    assert!(
+        Encoder::with_api_config(OpenH264API::from_source(), config)?
+            .encode(&yuv)
+            .is_err()
+    );
+
+    let config = EncoderConfig::new()
+        .usage_type(UsageType::ScreenContentRealTime)
+        .long_term_reference(true)
+        .ltr_ref_num(4);
+    Encoder::with_api_config(OpenH264API::from_source(), config)?.encode(&yuv)?;
+
+    let config = EncoderConfig::new()
+        .usage_type(UsageType::ScreenContentRealTime)
+        .long_term_reference(true)
+        .ltr_ref_num(5);
+    assert!(
+        Encoder::with_api_config(OpenH264API::from_source(), config)?
+            .encode(&yuv)
+            .is_err()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+#![cfg(all(feature = "source", feature = "log"))]
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use openh264::decoder::{Decoder, DecoderConfig};
+use openh264::encoder::{Encoder, EncoderConfig};
+use openh264::formats::YUVBuffer;
+use openh264::{Error, OpenH264API};
+use std::sync::Mutex;
+
+static MESSAGES: Mutex<Vec<(String, Level, String)>> = Mutex::new(Vec::new());
+
+struct Collector;
+
+impl Log for Collector {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let message = (record.target().to_string(), record.level(), record.args().to_string());
+        MESSAGES.lock().unwrap().push(message);
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn trace_output_goes_to_log() -> Result<(), Error> {
+    log::set_logger(&Collector).unwrap();
+
+    // Without `debug` OpenH264 stays quiet, whatever `log` would accept.
+    log::set_max_level(LevelFilter::Trace);
+    let mut encoder = Encoder::new()?;
+    encoder.encode(&YUVBuffer::new(64, 64))?;
+    let _decoder = Decoder::new()?;
+    assert!(MESSAGES.lock().unwrap().is_empty());
+
+    log::set_max_level(LevelFilter::Info);
+
+    let config = EncoderConfig::new().debug(true);
+    let mut encoder = Encoder::with_api_config(OpenH264API::from_source(), config)?;
+    encoder.encode(&YUVBuffer::new(64, 64))?;
+
+    let _decoder = Decoder::with_api_config(OpenH264API::from_source(), DecoderConfig::new().debug(true))?;
+
+    let messages = MESSAGES.lock().unwrap().clone();
+
+    assert!(messages.iter().any(|(target, _, _)| target == "openh264::encoder"));
+    assert!(messages.iter().any(|(target, _, _)| target == "openh264::decoder"));
+    assert!(messages.iter().all(|(_, level, _)| *level <= Level::Info));
+    assert!(
+        messages
+            .iter()
+            .all(|(_, _, message)| message.starts_with("encoder #") || message.starts_with("decoder #"))
+    );
+
+    Ok(())
+}